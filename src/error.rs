@@ -0,0 +1,109 @@
+use std::{error, fmt, io};
+
+/// An error encountered while reading a document, each carries the byte position in the input at which it was detected
+#[derive(Debug)]
+pub enum Error {
+    /// An end tag which doesn't close the currently open element
+    MismatchedEndTag {
+        expected: String,
+        found: String,
+        position: usize,
+    },
+    /// The input ended while there were still open elements
+    UnexpectedEof {
+        open_elements: usize,
+        position: usize,
+    },
+    /// Text, names or entities in the input which couldn't be decoded
+    Decoding {
+        source: quick_xml::Error,
+        position: usize,
+    },
+    /// Reading the input failed
+    Io { source: io::Error, position: usize },
+    /// Any other malformed markup
+    Syntax {
+        source: quick_xml::Error,
+        position: usize,
+    },
+}
+
+impl Error {
+    pub(crate) fn from_xml(error: quick_xml::Error, position: usize) -> Self {
+        match error {
+            quick_xml::Error::Io(source) => Error::Io { source, position },
+            quick_xml::Error::EndEventMismatch { expected, found } => Error::MismatchedEndTag {
+                expected,
+                found,
+                position,
+            },
+            source @ quick_xml::Error::Utf8(_) | source @ quick_xml::Error::EscapeError(_) => {
+                Error::Decoding { source, position }
+            }
+            source => Error::Syntax { source, position },
+        }
+    }
+
+    /// The byte position in the input at which the error was detected
+    pub fn position(&self) -> usize {
+        match *self {
+            Error::MismatchedEndTag { position, .. }
+            | Error::UnexpectedEof { position, .. }
+            | Error::Decoding { position, .. }
+            | Error::Io { position, .. }
+            | Error::Syntax { position, .. } => position,
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::MismatchedEndTag {
+                expected,
+                found,
+                position,
+            } => write!(
+                f,
+                "expected </{}> but found </{}> at position {}",
+                expected, found, position
+            ),
+            Error::UnexpectedEof {
+                open_elements,
+                position,
+            } => write!(
+                f,
+                "unexpected end of input with {} open elements at position {}",
+                open_elements, position
+            ),
+            Error::Decoding { source, position } => {
+                write!(f, "decoding error at position {}: {}", position, source)
+            }
+            Error::Io { source, position } => {
+                write!(f, "I/O error at position {}: {}", position, source)
+            }
+            Error::Syntax { source, position } => {
+                write!(f, "syntax error at position {}: {}", position, source)
+            }
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::MismatchedEndTag { .. } | Error::UnexpectedEof { .. } => None,
+            Error::Decoding { source, .. } | Error::Syntax { source, .. } => Some(source),
+            Error::Io { source, .. } => Some(source),
+        }
+    }
+}
+
+impl From<Error> for io::Error {
+    fn from(error: Error) -> Self {
+        match error {
+            Error::Io { ref source, .. } => io::Error::new(source.kind(), error),
+            error => io::Error::new(io::ErrorKind::InvalidData, error),
+        }
+    }
+}
@@ -0,0 +1,1148 @@
+use std::{
+    collections::{HashSet, VecDeque},
+    io::{self, BufRead, Write},
+};
+
+use quick_xml::events::{BytesEnd, BytesStart, BytesText, Event};
+
+use crate::iteritem::{EventReader, Item, Node};
+
+/// Elements which never have content or an end tag
+const VOID_ELEMENTS: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "param", "source",
+    "track", "wbr",
+];
+
+/// Elements whose start tag closes an open `p`
+const CLOSES_P: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "center",
+    "dd",
+    "details",
+    "dialog",
+    "dir",
+    "div",
+    "dl",
+    "dt",
+    "fieldset",
+    "figcaption",
+    "figure",
+    "footer",
+    "form",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hgroup",
+    "hr",
+    "li",
+    "listing",
+    "main",
+    "menu",
+    "nav",
+    "ol",
+    "p",
+    "plaintext",
+    "pre",
+    "section",
+    "summary",
+    "table",
+    "ul",
+    "xmp",
+];
+
+const HEADINGS: &[&str] = &["h1", "h2", "h3", "h4", "h5", "h6"];
+
+/// Elements which bound the search for an open element, an end tag can't close anything outside of them
+const SCOPE_BOUNDARIES: &[&str] = &[
+    "applet", "caption", "html", "table", "td", "th", "marquee", "object", "template",
+];
+
+const BUTTON_SCOPE_BOUNDARIES: &[&str] = &[
+    "applet", "caption", "html", "table", "td", "th", "marquee", "object", "template", "button",
+];
+
+const TABLE_SCOPE_BOUNDARIES: &[&str] = &["html", "table", "template"];
+
+const LIST_ITEM_SCOPE_BOUNDARIES: &[&str] = &[
+    "applet", "caption", "html", "table", "td", "th", "marquee", "object", "template", "ol", "ul",
+];
+
+/// Elements whose content is text which is never escaped, up to the matching end tag
+const RAW_TEXT_ELEMENTS: &[&str] = &[
+    "iframe", "noembed", "noframes", "noscript", "script", "style", "xmp",
+];
+
+/// Elements whose content is text with character references, up to the matching end tag
+const ESCAPABLE_RAW_TEXT_ELEMENTS: &[&str] = &["textarea", "title"];
+
+/// Attributes whose presence alone means true, which can be written without a value
+const BOOLEAN_ATTRIBUTES: &[&str] = &[
+    "allowfullscreen",
+    "async",
+    "autofocus",
+    "autoplay",
+    "checked",
+    "controls",
+    "default",
+    "defer",
+    "disabled",
+    "formnovalidate",
+    "hidden",
+    "inert",
+    "ismap",
+    "itemscope",
+    "loop",
+    "multiple",
+    "muted",
+    "nomodule",
+    "novalidate",
+    "open",
+    "playsinline",
+    "readonly",
+    "required",
+    "reversed",
+    "selected",
+];
+
+/// Elements which may be self-closed, as they can contain foreign content
+const FOREIGN_ELEMENTS: &[&str] = &["svg", "math"];
+
+/// Elements in svg and mathml whose content is html again
+const INTEGRATION_POINTS: &[&str] = &[
+    "foreignObject",
+    "desc",
+    "title",
+    "annotation-xml",
+    "mi",
+    "mo",
+    "mn",
+    "ms",
+    "mtext",
+];
+
+pub(crate) fn is_void(name: &str) -> bool {
+    contains(VOID_ELEMENTS, name)
+}
+
+fn contains(set: &[&str], name: &str) -> bool {
+    set.iter().any(|n| n.eq_ignore_ascii_case(name))
+}
+
+/// Whether the content of the innermost of the elements, given from the outermost, is foreign, as it's inside `svg` or `math` without an integration point in between
+fn in_foreign_content<'a>(path: impl DoubleEndedIterator<Item = &'a str>) -> bool {
+    for name in path.rev() {
+        if contains(INTEGRATION_POINTS, name) {
+            return false;
+        }
+        if contains(FOREIGN_ELEMENTS, name) {
+            return true;
+        }
+    }
+    false
+}
+
+/// A token which has been read, or synthesised by the tree construction rules, and not yet returned
+#[derive(Debug)]
+enum Token {
+    Start {
+        content: String,
+        name_len: usize,
+    },
+    Empty {
+        content: String,
+        name_len: usize,
+    },
+    End(String),
+    Text(String),
+    /// Text which must be written exactly as it was read
+    RawText(String),
+    Comment(String),
+    CData(String),
+    DocType(String),
+}
+
+/// Tokenizes html, following enough of the html 5 tree construction rules to produce a balanced stream of events
+///
+/// Void elements are produced as empty elements, missing end tags are inserted, stray end tags are dropped and named character references are normalised to xml escapes.
+/// Only the names of the open elements are kept, so memory is proportional to the depth of the document.
+pub(crate) struct HtmlReader<B: BufRead> {
+    reader: B,
+    position: usize,
+    /// Names of the elements currently open, as they were written in their start tags
+    open: Vec<String>,
+    pending: VecDeque<Token>,
+    /// The name of the raw text element whose content is to be read next, and whether it's escapable
+    raw_text: Option<(String, bool)>,
+    /// Whether the last event was raw text
+    last_raw: bool,
+    eof: bool,
+}
+
+impl<B: BufRead> HtmlReader<B> {
+    pub(crate) fn from_reader(reader: B) -> Self {
+        Self {
+            reader,
+            position: 0,
+            open: vec![],
+            pending: VecDeque::new(),
+            raw_text: None,
+            last_raw: false,
+            eof: false,
+        }
+    }
+
+    fn peek(&mut self) -> quick_xml::Result<Option<u8>> {
+        Ok(self.reader.fill_buf()?.first().copied())
+    }
+
+    fn bump(&mut self) {
+        self.reader.consume(1);
+        self.position += 1;
+    }
+
+    fn next_byte(&mut self) -> quick_xml::Result<Option<u8>> {
+        let byte = self.peek()?;
+        if byte.is_some() {
+            self.bump();
+        }
+        Ok(byte)
+    }
+
+    /// Reads bytes up to, but not including, the first byte which matches
+    fn read_while(
+        &mut self,
+        buf: &mut Vec<u8>,
+        mut predicate: impl FnMut(u8) -> bool,
+    ) -> quick_xml::Result<()> {
+        loop {
+            let available = self.reader.fill_buf()?;
+            if available.is_empty() {
+                return Ok(());
+            }
+            let len = available
+                .iter()
+                .position(|&b| !predicate(b))
+                .unwrap_or(available.len());
+            buf.extend_from_slice(&available[..len]);
+            let done = len < available.len();
+            self.reader.consume(len);
+            self.position += len;
+            if done {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Reads bytes until the buffer ends with the terminator, which is consumed but not included
+    fn read_until(&mut self, buf: &mut Vec<u8>, terminator: &[u8]) -> quick_xml::Result<()> {
+        while let Some(byte) = self.next_byte()? {
+            buf.push(byte);
+            if buf.ends_with(terminator) {
+                buf.truncate(buf.len() - terminator.len());
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    /// Reads the next token from the input, `None` at the end of the input
+    fn read_token(&mut self) -> quick_xml::Result<Option<Token>> {
+        loop {
+            match self.peek()? {
+                None => return Ok(None),
+                Some(b'<') => {
+                    self.bump();
+                    if let Some(token) = self.read_markup()? {
+                        return Ok(Some(token));
+                    }
+                }
+                Some(_) => {
+                    let mut text = vec![];
+                    self.read_while(&mut text, |b| b != b'<')?;
+                    return Ok(Some(Token::Text(normalise_text(&decode(text)?))));
+                }
+            }
+        }
+    }
+
+    /// Reads the content of a raw text element, and consumes its end tag
+    fn read_raw_text(&mut self, name: &str) -> quick_xml::Result<Vec<u8>> {
+        let mut text = vec![];
+        loop {
+            self.read_while(&mut text, |b| b != b'<')?;
+            if self.next_byte()?.is_none() {
+                return Ok(text);
+            }
+            let start = text.len();
+            text.push(b'<');
+            if self.peek()? == Some(b'/') {
+                self.bump();
+                text.push(b'/');
+                let mut tag = vec![];
+                self.read_while(&mut tag, |b| b.is_ascii_alphanumeric())?;
+                if tag.eq_ignore_ascii_case(name.as_bytes())
+                    && matches!(
+                        self.peek()?,
+                        None | Some(b'>' | b'/' | b' ' | b'\t' | b'\n' | b'\r' | b'\x0C')
+                    )
+                {
+                    self.read_until(&mut vec![], b">")?;
+                    text.truncate(start);
+                    return Ok(text);
+                }
+                text.extend(tag);
+            }
+        }
+    }
+
+    /// Reads markup after a `<`, `None` if it was dropped
+    fn read_markup(&mut self) -> quick_xml::Result<Option<Token>> {
+        match self.peek()? {
+            Some(b'!') => {
+                self.bump();
+                let mut content = vec![];
+                if self.peek()? == Some(b'-') {
+                    self.bump();
+                    if self.peek()? == Some(b'-') {
+                        self.bump();
+                        // `<!-->` and `<!--->` are empty comments
+                        if self.peek()? == Some(b'-') {
+                            self.bump();
+                            content.push(b'-');
+                        }
+                        if self.peek()? == Some(b'>') && content.len() <= 1 {
+                            self.bump();
+                            return Ok(Some(Token::Comment(String::new())));
+                        }
+                        self.read_until(&mut content, b"-->")?;
+                        return Ok(Some(Token::Comment(decode(content)?)));
+                    }
+                    content.push(b'-');
+                }
+                self.read_until(&mut content, b">")?;
+                if content.starts_with(b"[CDATA[") {
+                    while !content.ends_with(b"]]") && self.peek()?.is_some() {
+                        content.push(b'>');
+                        self.read_until(&mut content, b">")?;
+                    }
+                    // a section cut off by the end of the document has no `]]` to drop
+                    let end = if content.ends_with(b"]]") {
+                        content.len() - 2
+                    } else {
+                        content.len()
+                    };
+                    let end = end.max(7);
+                    return Ok(Some(Token::CData(decode(content[7..end].to_vec())?)));
+                }
+                if content.len() >= 7 && content[..7].eq_ignore_ascii_case(b"doctype") {
+                    return Ok(Some(Token::DocType(decode(content[7..].to_vec())?)));
+                }
+                Ok(Some(Token::Comment(decode(content)?)))
+            }
+            Some(b'?') => {
+                let mut content = vec![];
+                self.read_until(&mut content, b">")?;
+                Ok(Some(Token::Comment(decode(content)?)))
+            }
+            Some(b'/') => {
+                self.bump();
+                match self.peek()? {
+                    Some(b) if b.is_ascii_alphabetic() => {
+                        let mut name = vec![];
+                        self.read_while(&mut name, is_name_byte)?;
+                        self.read_until(&mut vec![], b">")?;
+                        Ok(Some(Token::End(decode(name)?)))
+                    }
+                    Some(b'>') => {
+                        self.bump();
+                        Ok(None)
+                    }
+                    _ => {
+                        let mut content = vec![];
+                        self.read_until(&mut content, b">")?;
+                        Ok(Some(Token::Comment(decode(content)?)))
+                    }
+                }
+            }
+            Some(b) if b.is_ascii_alphabetic() => self.read_start_tag(),
+            _ => Ok(Some(Token::Text("&lt;".to_string()))),
+        }
+    }
+
+    /// Reads a start tag, normalising its attributes to double quoted, escaped and unique
+    fn read_start_tag(&mut self) -> quick_xml::Result<Option<Token>> {
+        let mut name = vec![];
+        self.read_while(&mut name, is_name_byte)?;
+        let mut content = decode(name)?;
+        let name_len = content.len();
+        let mut attr_names = HashSet::new();
+        let mut self_closing = false;
+        loop {
+            let mut skipped = vec![];
+            self.read_while(&mut skipped, |b| b.is_ascii_whitespace())?;
+            match self.next_byte()? {
+                None => return Ok(None),
+                Some(b'>') => break,
+                Some(b'/') => {
+                    self_closing = self.peek()? == Some(b'>');
+                    continue;
+                }
+                Some(first) => {
+                    let mut attr_name = vec![first];
+                    self.read_while(&mut attr_name, |b| {
+                        !b.is_ascii_whitespace() && !matches!(b, b'/' | b'>' | b'=')
+                    })?;
+                    let attr_name = decode(attr_name)?;
+                    skipped.clear();
+                    self.read_while(&mut skipped, |b| b.is_ascii_whitespace())?;
+                    let mut value = vec![];
+                    if self.peek()? == Some(b'=') {
+                        self.bump();
+                        self.read_while(&mut skipped, |b| b.is_ascii_whitespace())?;
+                        match self.peek()? {
+                            Some(quote @ b'"') | Some(quote @ b'\'') => {
+                                self.bump();
+                                self.read_until(&mut value, &[quote])?;
+                            }
+                            _ => self.read_while(&mut value, |b| {
+                                !b.is_ascii_whitespace() && b != b'>'
+                            })?,
+                        }
+                    }
+                    // later duplicates of an attribute are ignored, and quick-xml would reject them, as would names which can't be written back
+                    if attr_name.contains(['"', '\'', '<', '='])
+                        || !attr_names.insert(attr_name.to_ascii_lowercase())
+                    {
+                        continue;
+                    }
+                    content.push(' ');
+                    content.push_str(&attr_name);
+                    content.push_str("=\"");
+                    content.push_str(&normalise_attribute(&decode(value)?));
+                    content.push('"');
+                }
+            }
+        }
+        let name = &content[..name_len];
+        if contains(VOID_ELEMENTS, name)
+            || (self_closing
+                && (contains(FOREIGN_ELEMENTS, name)
+                    || in_foreign_content(self.open.iter().map(String::as_str))))
+        {
+            Ok(Some(Token::Empty { content, name_len }))
+        } else {
+            Ok(Some(Token::Start { content, name_len }))
+        }
+    }
+
+    /// Finds the index of the innermost open element in `targets`, without looking past any of the `boundaries`
+    fn find_in_scope(&self, targets: &[&str], boundaries: &[&str]) -> Option<usize> {
+        for (i, name) in self.open.iter().enumerate().rev() {
+            if contains(targets, name) {
+                return Some(i);
+            }
+            if contains(boundaries, name) {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// Closes the open elements from the innermost up to and including the one at `index`
+    fn close_to(&mut self, index: usize) {
+        while self.open.len() > index {
+            let name = self.open.pop().unwrap();
+            self.pending.push_back(Token::End(name));
+        }
+    }
+
+    fn close_in_scope(&mut self, targets: &[&str], boundaries: &[&str]) {
+        if let Some(index) = self.find_in_scope(targets, boundaries) {
+            self.close_to(index)
+        }
+    }
+
+    /// Applies the tree construction rules to a token, queuing it and any end tags it implies
+    fn construct(&mut self, token: Token) {
+        match token {
+            Token::Start {
+                ref content,
+                name_len,
+            }
+            | Token::Empty {
+                ref content,
+                name_len,
+            } => {
+                let name = content[..name_len].to_ascii_lowercase();
+                match name.as_str() {
+                    "li" => self.close_in_scope(&["li"], LIST_ITEM_SCOPE_BOUNDARIES),
+                    "dd" | "dt" => self.close_in_scope(&["dd", "dt"], SCOPE_BOUNDARIES),
+                    "tr" => self.close_in_scope(&["tr"], TABLE_SCOPE_BOUNDARIES),
+                    "td" | "th" => {
+                        self.close_in_scope(&["td", "th"], &["tr", "table", "template", "html"])
+                    }
+                    "thead" | "tbody" | "tfoot" => {
+                        self.close_in_scope(&["thead", "tbody", "tfoot"], TABLE_SCOPE_BOUNDARIES)
+                    }
+                    "option" => self.close_in_scope(&["option"], &["select", "datalist"]),
+                    "optgroup" => {
+                        self.close_in_scope(&["option", "optgroup"], &["select", "datalist"])
+                    }
+                    "body" => self.close_in_scope(&["head"], &["html"]),
+                    _ => {}
+                }
+                if contains(CLOSES_P, &name) {
+                    self.close_in_scope(&["p"], BUTTON_SCOPE_BOUNDARIES);
+                }
+                if contains(HEADINGS, &name)
+                    && self.open.last().map_or(false, |n| contains(HEADINGS, n))
+                {
+                    self.close_to(self.open.len() - 1);
+                }
+                if let Token::Start { .. } = token {
+                    let name = &content[..name_len];
+                    // elements such as title and style are ordinary elements in svg and mathml
+                    if !in_foreign_content(self.open.iter().map(String::as_str)) {
+                        if contains(RAW_TEXT_ELEMENTS, name) {
+                            self.raw_text = Some((name.to_string(), false));
+                        } else if contains(ESCAPABLE_RAW_TEXT_ELEMENTS, name) {
+                            self.raw_text = Some((name.to_string(), true));
+                        }
+                    }
+                    self.open.push(name.to_string());
+                }
+                self.pending.push_back(token);
+            }
+            Token::End(ref name) => {
+                if contains(VOID_ELEMENTS, name) {
+                    return;
+                }
+                let lowercase = name.to_ascii_lowercase();
+                let index = match lowercase.as_str() {
+                    // closing any cells and rows left open inside them
+                    "table" | "thead" | "tbody" | "tfoot" | "tr" => {
+                        self.find_in_scope(&[name], TABLE_SCOPE_BOUNDARIES)
+                    }
+                    "p" => self.find_in_scope(&[name], BUTTON_SCOPE_BOUNDARIES),
+                    "li" => self.find_in_scope(&[name], LIST_ITEM_SCOPE_BOUNDARIES),
+                    // any heading's end tag closes the open heading
+                    name if contains(HEADINGS, name) => {
+                        self.find_in_scope(HEADINGS, SCOPE_BOUNDARIES)
+                    }
+                    _ => self.find_in_scope(&[name], SCOPE_BOUNDARIES),
+                };
+                // stray end tags, which don't match an element in scope, are ignored
+                if let Some(index) = index {
+                    self.close_to(index);
+                }
+            }
+            token => self.pending.push_back(token),
+        }
+    }
+}
+
+impl<B: BufRead> EventReader for HtmlReader<B> {
+    fn read_event<'b>(&mut self, buf: &'b mut Vec<u8>) -> quick_xml::Result<Event<'b>> {
+        while self.pending.is_empty() && !self.eof {
+            if let Some((name, escapable)) = self.raw_text.take() {
+                let text = decode(self.read_raw_text(&name)?)?;
+                if !text.is_empty() {
+                    self.pending.push_back(if escapable {
+                        Token::Text(normalise_text(&text))
+                    } else {
+                        Token::RawText(text)
+                    });
+                }
+                self.construct(Token::End(name));
+                continue;
+            }
+            match self.read_token()? {
+                Some(token) => self.construct(token),
+                None => {
+                    self.eof = true;
+                    self.close_to(0);
+                }
+            }
+        }
+        buf.clear();
+        let token = self.pending.pop_front();
+        self.last_raw = matches!(token, Some(Token::RawText(_)));
+        Ok(match token {
+            None => Event::Eof,
+            Some(Token::Start { content, name_len }) => {
+                buf.extend(content.into_bytes());
+                Event::Start(BytesStart::borrowed(buf, name_len))
+            }
+            Some(Token::Empty { content, name_len }) => {
+                buf.extend(content.into_bytes());
+                Event::Empty(BytesStart::borrowed(buf, name_len))
+            }
+            Some(Token::End(name)) => {
+                buf.extend(name.into_bytes());
+                Event::End(BytesEnd::borrowed(buf))
+            }
+            Some(Token::Text(text)) => {
+                buf.extend(text.into_bytes());
+                Event::Text(BytesText::from_escaped(&buf[..]))
+            }
+            Some(Token::RawText(text)) => {
+                buf.extend(text.into_bytes());
+                Event::Text(BytesText::from_escaped(&buf[..]))
+            }
+            Some(Token::Comment(text)) => {
+                buf.extend(text.into_bytes());
+                Event::Comment(BytesText::from_escaped(&buf[..]))
+            }
+            Some(Token::CData(text)) => Event::CData(BytesText::from_plain_str(&text).into_owned()),
+            Some(Token::DocType(text)) => {
+                buf.extend(text.into_bytes());
+                Event::DocType(BytesText::from_escaped(&buf[..]))
+            }
+        })
+    }
+
+    fn buffer_position(&self) -> usize {
+        self.position
+    }
+
+    fn raw_text(&self) -> bool {
+        self.last_raw
+    }
+}
+
+/// Writes an item using the html 5 serialisation, void elements have no end tags, other elements are never self-closing outside of foreign content, and text is escaped as html
+pub(crate) fn write_item(
+    w: &mut impl Write,
+    item: &Item<'_>,
+    minimise_boolean_attributes: bool,
+) -> io::Result<()> {
+    let foreign = item
+        .as_element()
+        .map_or(false, |e| contains(FOREIGN_ELEMENTS, e.name()))
+        || in_foreign_content(item.context_path().iter().map(|e| e.name()));
+    match item.node() {
+        Node::Start | Node::Empty => {
+            let element = item.as_element().unwrap();
+            write!(w, "<{}", element.name())?;
+            for (name, value) in element.attributes() {
+                if minimise_boolean_attributes
+                    && contains(BOOLEAN_ATTRIBUTES, name)
+                    && (value.is_empty() || value.eq_ignore_ascii_case(name))
+                {
+                    write!(w, " {}", name)?;
+                } else {
+                    write!(w, " {}=\"", name)?;
+                    write_escaped(w, value, true)?;
+                    w.write_all(b"\"")?;
+                }
+            }
+            let void = contains(VOID_ELEMENTS, element.name());
+            match item.node() {
+                Node::Empty if foreign && !void => w.write_all(b"/>"),
+                Node::Empty if !void => write!(w, "></{}>", element.name()),
+                _ => w.write_all(b">"),
+            }
+        }
+        Node::End => {
+            let element = item.as_element().unwrap();
+            if contains(VOID_ELEMENTS, element.name()) {
+                Ok(())
+            } else {
+                write!(w, "</{}>", element.name())
+            }
+        }
+        Node::Text | Node::RawText => {
+            let raw_text_parent = item
+                .as_path()
+                .into_iter()
+                .next_back()
+                .map(|e| e.name())
+                .filter(|name| !foreign && contains(RAW_TEXT_ELEMENTS, name));
+            match raw_text_parent {
+                Some(name) => {
+                    let text = item.text().unwrap();
+                    if contains_end_tag(&text, name) {
+                        return Err(io::Error::new(
+                            io::ErrorKind::InvalidData,
+                            format!(
+                                "the content of a {} element can't contain its end tag",
+                                name
+                            ),
+                        ));
+                    }
+                    w.write_all(text.as_bytes())
+                }
+                None if matches!(item.node(), Node::RawText) => {
+                    w.write_all(item.content().as_bytes())
+                }
+                // the text is already escaped for xml, which is also valid html
+                None => write_nbsp_escaped(w, item.content()),
+            }
+        }
+        Node::CData if foreign => write!(w, "<![CDATA[{}]]>", item.content()),
+        Node::CData => write_escaped(w, item.content(), false),
+        Node::Comment => write!(w, "<!--{}-->", item.content()),
+        Node::DocType => write!(w, "<!DOCTYPE{}>", item.content()),
+        // the xml declaration has no meaning in html
+        Node::Decl => Ok(()),
+        Node::PI => write!(w, "<?{}?>", item.content()),
+    }
+}
+
+/// Whether the text contains an end tag for the element, which would end its raw text early
+fn contains_end_tag(text: &str, name: &str) -> bool {
+    text.match_indices("</").any(|(i, _)| {
+        text.as_bytes()[i + 2..]
+            .get(..name.len())
+            .map_or(false, |tag| tag.eq_ignore_ascii_case(name.as_bytes()))
+    })
+}
+
+/// Writes text which is already escaped, escaping non-breaking spaces as html does
+fn write_nbsp_escaped(w: &mut impl Write, text: &str) -> io::Result<()> {
+    let mut pieces = text.split('\u{A0}');
+    if let Some(first) = pieces.next() {
+        w.write_all(first.as_bytes())?;
+    }
+    for piece in pieces {
+        w.write_all(b"&nbsp;")?;
+        w.write_all(piece.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Escapes text or a double quoted attribute value following the html 5 serialisation rules
+fn write_escaped(w: &mut impl Write, text: &str, attribute: bool) -> io::Result<()> {
+    let mut rest = text;
+    while let Some(i) = rest.find(['&', '\u{A0}', '"', '<', '>']) {
+        w.write_all(&rest.as_bytes()[..i])?;
+        let c = rest[i..].chars().next().unwrap();
+        let escaped = match c {
+            '&' => "&amp;",
+            '\u{A0}' => "&nbsp;",
+            '"' if attribute => "&quot;",
+            '<' if !attribute => "&lt;",
+            '>' if !attribute => "&gt;",
+            _ => &rest[i..i + 1],
+        };
+        w.write_all(escaped.as_bytes())?;
+        rest = &rest[i + c.len_utf8()..];
+    }
+    w.write_all(rest.as_bytes())
+}
+
+fn is_name_byte(b: u8) -> bool {
+    !b.is_ascii_whitespace() && !matches!(b, b'/' | b'>')
+}
+
+fn decode(bytes: Vec<u8>) -> quick_xml::Result<String> {
+    String::from_utf8(bytes).map_err(|e| quick_xml::Error::Utf8(e.utf8_error()))
+}
+
+/// Replaces html character references with their characters and escapes the result as xml text
+fn normalise_text(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for_each_char(text, |c| match c {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        c => out.push(c),
+    });
+    out
+}
+
+/// Replaces html character references with their characters and escapes the result as a double quoted xml attribute value
+fn normalise_attribute(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for_each_char(value, |c| match c {
+        '&' => out.push_str("&amp;"),
+        '<' => out.push_str("&lt;"),
+        '>' => out.push_str("&gt;"),
+        '"' => out.push_str("&quot;"),
+        c => out.push(c),
+    });
+    out
+}
+
+/// Calls `f` with each character of the text, with character references resolved, references which aren't recognised are left as they are
+fn for_each_char(mut text: &str, mut f: impl FnMut(char)) {
+    while let Some(amp) = text.find('&') {
+        text[..amp].chars().for_each(&mut f);
+        text = &text[amp..];
+        match resolve_reference(text) {
+            Some((c, len)) => {
+                f(c);
+                text = &text[len..];
+            }
+            None => {
+                f('&');
+                text = &text[1..];
+            }
+        }
+    }
+    text.chars().for_each(f)
+}
+
+/// Resolves a character reference at the start of the text, returning the character and the length of the reference
+fn resolve_reference(text: &str) -> Option<(char, usize)> {
+    // only as far as the longest reference, so that text full of `&`s isn't searched to its end for each of them
+    let end = text.as_bytes()[1..]
+        .iter()
+        .take(MAX_REFERENCE_LEN)
+        .position(|&b| b == b';')?
+        + 1;
+    let reference = &text[1..end];
+    let c = if let Some(number) = reference.strip_prefix('#') {
+        let code = match number.strip_prefix(['x', 'X']) {
+            Some(hex) => u32::from_str_radix(hex, 16).ok()?,
+            None => number.parse().ok()?,
+        };
+        char::from_u32(code)
+            .filter(|&c| c != '\0')
+            .unwrap_or('\u{FFFD}')
+    } else {
+        NAMED_REFERENCES
+            .iter()
+            .find(|(name, _)| *name == reference)?
+            .1
+    };
+    Some((c, end + 1))
+}
+
+/// Longer than any of the named references, and any numeric reference without a run of leading zeros
+const MAX_REFERENCE_LEN: usize = 32;
+
+/// The commonly used named character references, the Latin-1 set and a selection of punctuation and symbols
+const NAMED_REFERENCES: &[(&str, char)] = &[
+    ("amp", '&'),
+    ("lt", '<'),
+    ("gt", '>'),
+    ("quot", '"'),
+    ("apos", '\''),
+    ("nbsp", '\u{A0}'),
+    ("iexcl", '¡'),
+    ("cent", '¢'),
+    ("pound", '£'),
+    ("curren", '¤'),
+    ("yen", '¥'),
+    ("brvbar", '¦'),
+    ("sect", '§'),
+    ("uml", '¨'),
+    ("copy", '©'),
+    ("ordf", 'ª'),
+    ("laquo", '«'),
+    ("not", '¬'),
+    ("shy", '\u{AD}'),
+    ("reg", '®'),
+    ("macr", '¯'),
+    ("deg", '°'),
+    ("plusmn", '±'),
+    ("sup2", '²'),
+    ("sup3", '³'),
+    ("acute", '´'),
+    ("micro", 'µ'),
+    ("para", '¶'),
+    ("middot", '·'),
+    ("cedil", '¸'),
+    ("sup1", '¹'),
+    ("ordm", 'º'),
+    ("raquo", '»'),
+    ("frac14", '¼'),
+    ("frac12", '½'),
+    ("frac34", '¾'),
+    ("iquest", '¿'),
+    ("Agrave", 'À'),
+    ("Aacute", 'Á'),
+    ("Acirc", 'Â'),
+    ("Atilde", 'Ã'),
+    ("Auml", 'Ä'),
+    ("Aring", 'Å'),
+    ("AElig", 'Æ'),
+    ("Ccedil", 'Ç'),
+    ("Egrave", 'È'),
+    ("Eacute", 'É'),
+    ("Ecirc", 'Ê'),
+    ("Euml", 'Ë'),
+    ("Igrave", 'Ì'),
+    ("Iacute", 'Í'),
+    ("Icirc", 'Î'),
+    ("Iuml", 'Ï'),
+    ("ETH", 'Ð'),
+    ("Ntilde", 'Ñ'),
+    ("Ograve", 'Ò'),
+    ("Oacute", 'Ó'),
+    ("Ocirc", 'Ô'),
+    ("Otilde", 'Õ'),
+    ("Ouml", 'Ö'),
+    ("times", '×'),
+    ("Oslash", 'Ø'),
+    ("Ugrave", 'Ù'),
+    ("Uacute", 'Ú'),
+    ("Ucirc", 'Û'),
+    ("Uuml", 'Ü'),
+    ("Yacute", 'Ý'),
+    ("THORN", 'Þ'),
+    ("szlig", 'ß'),
+    ("agrave", 'à'),
+    ("aacute", 'á'),
+    ("acirc", 'â'),
+    ("atilde", 'ã'),
+    ("auml", 'ä'),
+    ("aring", 'å'),
+    ("aelig", 'æ'),
+    ("ccedil", 'ç'),
+    ("egrave", 'è'),
+    ("eacute", 'é'),
+    ("ecirc", 'ê'),
+    ("euml", 'ë'),
+    ("igrave", 'ì'),
+    ("iacute", 'í'),
+    ("icirc", 'î'),
+    ("iuml", 'ï'),
+    ("eth", 'ð'),
+    ("ntilde", 'ñ'),
+    ("ograve", 'ò'),
+    ("oacute", 'ó'),
+    ("ocirc", 'ô'),
+    ("otilde", 'õ'),
+    ("ouml", 'ö'),
+    ("divide", '÷'),
+    ("oslash", 'ø'),
+    ("ugrave", 'ù'),
+    ("uacute", 'ú'),
+    ("ucirc", 'û'),
+    ("uuml", 'ü'),
+    ("yacute", 'ý'),
+    ("thorn", 'þ'),
+    ("yuml", 'ÿ'),
+    ("OElig", 'Œ'),
+    ("oelig", 'œ'),
+    ("Scaron", 'Š'),
+    ("scaron", 'š'),
+    ("Yuml", 'Ÿ'),
+    ("fnof", 'ƒ'),
+    ("circ", 'ˆ'),
+    ("tilde", '˜'),
+    ("ensp", '\u{2002}'),
+    ("emsp", '\u{2003}'),
+    ("thinsp", '\u{2009}'),
+    ("zwnj", '\u{200C}'),
+    ("zwj", '\u{200D}'),
+    ("lrm", '\u{200E}'),
+    ("rlm", '\u{200F}'),
+    ("ndash", '–'),
+    ("mdash", '—'),
+    ("lsquo", '‘'),
+    ("rsquo", '’'),
+    ("sbquo", '‚'),
+    ("ldquo", '“'),
+    ("rdquo", '”'),
+    ("bdquo", '„'),
+    ("dagger", '†'),
+    ("Dagger", '‡'),
+    ("bull", '•'),
+    ("hellip", '…'),
+    ("permil", '‰'),
+    ("prime", '′'),
+    ("Prime", '″'),
+    ("lsaquo", '‹'),
+    ("rsaquo", '›'),
+    ("oline", '‾'),
+    ("frasl", '⁄'),
+    ("euro", '€'),
+    ("trade", '™'),
+    ("larr", '←'),
+    ("uarr", '↑'),
+    ("rarr", '→'),
+    ("darr", '↓'),
+    ("harr", '↔'),
+    ("minus", '−'),
+    ("le", '≤'),
+    ("ge", '≥'),
+    ("ne", '≠'),
+    ("infin", '∞'),
+    ("loz", '◊'),
+    ("spades", '♠'),
+    ("clubs", '♣'),
+    ("hearts", '♥'),
+    ("diams", '♦'),
+];
+
+#[cfg(test)]
+mod test {
+    use crate::{HtmlIter, HtmlIterator, HtmlWriter};
+
+    fn parse(html: &str) -> String {
+        HtmlIter::from_html_reader(html.as_bytes()).to_string()
+    }
+
+    fn serialise(iter: impl HtmlIterator, minimise_boolean_attributes: bool) -> String {
+        let mut out = vec![];
+        let mut writer = HtmlWriter::from_html_writer(&mut out);
+        writer.minimise_boolean_attributes(minimise_boolean_attributes);
+        iter.write_with(&mut writer).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn html_serialisation() {
+        let html = r#"<!DOCTYPE html><p class="a&amp;&quot;b">a&nbsp;&lt;b<br><input checked disabled="disabled" value=""></p><div></div><svg><path d="M0"/></svg><script>a < b</script>"#;
+        assert_eq!(
+            serialise(HtmlIter::from_html_reader(html.as_bytes()), true),
+            r#"<!DOCTYPE html><p class="a&amp;&quot;b">a&nbsp;&lt;b<br><input checked disabled value=""></p><div></div><svg><path d="M0"/></svg><script>a < b</script>"#
+        );
+        assert_eq!(
+            serialise(HtmlIter::from_html_reader(html.as_bytes()), false),
+            r#"<!DOCTYPE html><p class="a&amp;&quot;b">a&nbsp;&lt;b<br><input checked="" disabled="disabled" value=""></p><div></div><svg><path d="M0"/></svg><script>a < b</script>"#
+        );
+        let xml = r#"<?xml version="1.0"?><p><br></br><div/><style>a &gt; b</style></p>"#;
+        assert_eq!(
+            serialise(HtmlIter::from_reader(xml.as_bytes()), false),
+            "<p><br><div></div><style>a > b</style></p>"
+        );
+        let xml = r#"<svg><foreignObject><br/><div/><style>a &gt; b</style></foreignObject><g/><style>a &gt; b</style></svg>"#;
+        assert_eq!(
+            serialise(HtmlIter::from_reader(xml.as_bytes()), false),
+            "<svg><foreignObject><br><div></div><style>a > b</style></foreignObject><g/><style>a &gt; b</style></svg>"
+        );
+        let broken = "<script>a &lt;/script&gt;</script>";
+        assert!(HtmlIter::from_reader(broken.as_bytes())
+            .write_with(&mut HtmlWriter::from_html_writer(vec![]))
+            .is_err());
+    }
+
+    #[test]
+    fn void_elements() {
+        assert_eq!(
+            parse(r#"<p>a<br>b<img src="a.png"></p>"#),
+            r#"<p>a<br/>b<img src="a.png"/></p>"#
+        );
+        assert_eq!(parse("<p>a<br/>b</br></p>"), "<p>a<br/>b</p>");
+    }
+
+    #[test]
+    fn attributes() {
+        assert_eq!(
+            parse(r#"<input type=checkbox checked value='say "hi"' TYPE="x">"#),
+            r#"<input type="checkbox" checked="" value="say &quot;hi&quot;"/>"#
+        );
+        assert_eq!(parse("<div =x id=a>b</div>"), r#"<div id="a">b</div>"#);
+    }
+
+    #[test]
+    fn implied_end_tags() {
+        assert_eq!(
+            parse("<p>one<p>two<div>three</div>"),
+            "<p>one</p><p>two</p><div>three</div>"
+        );
+        assert_eq!(
+            parse("<ul><li>one<li>two<ul><li>nested</ul></ul>"),
+            "<ul><li>one</li><li>two<ul><li>nested</li></ul></li></ul>"
+        );
+        assert_eq!(
+            parse("<table><tr><td>a<td>b<tr><td>c</table>"),
+            "<table><tr><td>a</td><td>b</td></tr><tr><td>c</td></tr></table>"
+        );
+        assert_eq!(
+            parse("<table><tr><td>a</table><p>after</p>"),
+            "<table><tr><td>a</td></tr></table><p>after</p>"
+        );
+        assert_eq!(
+            parse("<table><tbody><tr><th>a</tbody><tr><td>b</tr></table>c"),
+            "<table><tbody><tr><th>a</th></tr></tbody><tr><td>b</td></tr></table>c"
+        );
+        assert_eq!(
+            parse("<table><tr><td><table><tr><td>a</table>b</td></tr></table>"),
+            "<table><tr><td><table><tr><td>a</td></tr></table>b</td></tr></table>"
+        );
+        assert_eq!(parse("<h1>a<h2>b</h1>c"), "<h1>a</h1><h2>b</h2>c");
+        assert_eq!(
+            parse("<ul><li>a<div>b</li>c</ul>"),
+            "<ul><li>a<div>b</div></li>c</ul>"
+        );
+        assert_eq!(
+            parse("<button><p>a</button>b</p>"),
+            "<button><p>a</p></button>b"
+        );
+        assert_eq!(
+            parse("<dl><dt>term<dd>definition</dl>"),
+            "<dl><dt>term</dt><dd>definition</dd></dl>"
+        );
+    }
+
+    #[test]
+    fn stray_end_tags() {
+        assert_eq!(parse("<div>a</span>b</div></div>"), "<div>ab</div>");
+        assert_eq!(parse("<div><b>a</div>"), "<div><b>a</b></div>");
+        assert_eq!(parse("<div><p>a"), "<div><p>a</p></div>");
+    }
+
+    #[test]
+    fn character_references() {
+        assert_eq!(
+            parse("<p>&copy; a &amp; b &nbsp;&#65;&#x42; & &unknown;</p>"),
+            "<p>© a &amp; b \u{A0}AB &amp; &amp;unknown;</p>"
+        );
+    }
+
+    #[test]
+    fn raw_text() {
+        let script = "<script>if (a < b && c > d) { x = '</p>&amp;'; }</script>";
+        assert_eq!(parse(script), script);
+        assert_eq!(
+            parse("<style>p > b { content: \"&\" }</STYLE >"),
+            "<style>p > b { content: \"&\" }</style>"
+        );
+        assert_eq!(
+            parse("<title>a &amp; <b>b</b></title>"),
+            "<title>a &amp; &lt;b&gt;b&lt;/b&gt;</title>"
+        );
+        assert_eq!(
+            parse("<script>unclosed <p>"),
+            "<script>unclosed <p></script>"
+        );
+
+        // inside svg and mathml they have elements and escaped text
+        assert_eq!(
+            parse("<svg><title>a<b>x</b></title><style>a &lt; b</style></svg><title>a<b>x</b></title>"),
+            "<svg><title>a<b>x</b></title><style>a &lt; b</style></svg><title>a&lt;b&gt;x&lt;/b&gt;</title>"
+        );
+        // except inside integration points such as foreignObject, which have html content again
+        assert_eq!(
+            parse("<svg><foreignObject><div/>a</foreignObject><g/></svg>"),
+            "<svg><foreignObject><div>a</div></foreignObject><g/></svg>"
+        );
+        assert_eq!(
+            parse("<svg><foreignObject><style>a < b</style><textarea>a<b>x</b></textarea><br/></foreignObject><desc><title>c<p>d</title></desc></svg>"),
+            "<svg><foreignObject><style>a < b</style><textarea>a&lt;b&gt;x&lt;/b&gt;</textarea><br/></foreignObject><desc><title>c&lt;p&gt;d</title></desc></svg>"
+        );
+
+        let mut iter = HtmlIter::from_html_reader("<script>a<b</script>".as_bytes());
+        iter.next();
+        assert_eq!(format!("{:?}", iter.next().unwrap()), r#"/script/"a<b""#);
+    }
+
+    #[test]
+    fn other_markup() {
+        assert_eq!(
+            parse("<!doctype html><!-- comment --><p>a < b</p>"),
+            "<!DOCTYPE html><!-- comment --><p>a &lt; b</p>"
+        );
+        assert_eq!(
+            parse("<!--><p>kept</p><!---><!----><!-- x -->"),
+            "<!----><p>kept</p><!----><!----><!-- x -->"
+        );
+        assert_eq!(parse("<p>a</p><![CDATA[abc"), "<p>a</p><![CDATA[abc]]>");
+        assert_eq!(
+            parse("<p>a</p><![CDATA[a]]b]]>"),
+            "<p>a</p><![CDATA[a]]b]]>"
+        );
+    }
+}
@@ -1,119 +1,592 @@
-use std::{fmt, io::BufRead, mem};
+use std::{
+    borrow::Cow,
+    collections::{hash_map::RandomState, HashMap},
+    fmt,
+    hash::{BuildHasher, Hasher},
+    io::BufRead,
+    mem, str,
+};
+
+use quick_xml::events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event};
 
-use quick_xml::{
-    events::{BytesEnd, BytesStart, BytesText, Event},
-    Reader,
+use crate::{
+    map::{EditAttributes, ElementMap, MapElement, Unchanged},
+    selector::ContextualSelector,
+    DocumentMode, Error, MATHML_NAMESPACE, SVG_NAMESPACE, XHTML_NAMESPACE,
 };
 
-use crate::selector::ContextualSelector;
+/// A source of xml events for the traverser to read
+pub(crate) trait EventReader {
+    fn read_event<'b>(&mut self, buf: &'b mut Vec<u8>) -> quick_xml::Result<Event<'b>>;
+
+    /// Reads an event along with the namespace URI its element name is bound to, readers which don't resolve namespaces leave it to the traverser
+    fn read_namespaced_event<'b, 'c>(
+        &mut self,
+        buf: &'b mut Vec<u8>,
+        _ns_buf: &'c mut Vec<u8>,
+    ) -> quick_xml::Result<(Option<&'c [u8]>, Event<'b>)> {
+        self.read_event(buf).map(|event| (None, event))
+    }
+
+    /// The byte position in the input
+    fn buffer_position(&self) -> usize;
+
+    /// Whether the last event was the content of a raw text element, which needs to be written without escaping
+    fn raw_text(&self) -> bool {
+        false
+    }
+}
+
+impl<B: BufRead> EventReader for quick_xml::Reader<B> {
+    fn read_event<'b>(&mut self, buf: &'b mut Vec<u8>) -> quick_xml::Result<Event<'b>> {
+        quick_xml::Reader::read_event(self, buf)
+    }
+
+    fn read_namespaced_event<'b, 'c>(
+        &mut self,
+        buf: &'b mut Vec<u8>,
+        ns_buf: &'c mut Vec<u8>,
+    ) -> quick_xml::Result<(Option<&'c [u8]>, Event<'b>)> {
+        quick_xml::Reader::read_namespaced_event(self, buf, ns_buf)
+    }
+
+    fn buffer_position(&self) -> usize {
+        quick_xml::Reader::buffer_position(self)
+    }
+}
+
+/// Compares element or attribute names, which are ASCII case-insensitive in html
+pub(crate) fn names_eq(html: bool, a: &str, b: &str) -> bool {
+    if html {
+        a.eq_ignore_ascii_case(b)
+    } else {
+        a == b
+    }
+}
+
+fn decode(bytes: &[u8], position: usize) -> Result<&str, Error> {
+    str::from_utf8(bytes).map_err(|e| Error::from_xml(e.into(), position))
+}
+
+/// Escapes the characters which can't appear in text content, or in a double quoted attribute value
+struct Escape<'a> {
+    text: &'a str,
+    attribute: bool,
+}
+
+impl fmt::Display for Escape<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let special: &[char] = if self.attribute {
+            &['&', '<', '"']
+        } else {
+            &['&', '<', '>']
+        };
+        let mut rest = self.text;
+        while let Some(i) = rest.find(special) {
+            f.write_str(&rest[..i])?;
+            f.write_str(match rest.as_bytes()[i] {
+                b'&' => "&amp;",
+                b'<' => "&lt;",
+                b'>' => "&gt;",
+                _ => "&quot;",
+            })?;
+            rest = &rest[i + 1..];
+        }
+        f.write_str(rest)
+    }
+}
+
+fn escape(text: &str, attribute: bool) -> Escape<'_> {
+    Escape { text, attribute }
+}
+
+/// Calls `push` with the pieces of the text with the predefined xml entities and character references replaced
+fn unescape_with(raw: &str, mut push: impl FnMut(&str)) -> Result<(), ()> {
+    let mut rest = raw;
+    while let Some(amp) = rest.find('&') {
+        push(&rest[..amp]);
+        let end = amp + rest[amp..].find(';').ok_or(())?;
+        let c = match &rest[amp + 1..end] {
+            "lt" => '<',
+            "gt" => '>',
+            "amp" => '&',
+            "apos" => '\'',
+            "quot" => '"',
+            reference => {
+                let code = match reference.strip_prefix("#x") {
+                    Some(hex) => u32::from_str_radix(hex, 16),
+                    None => reference.strip_prefix('#').ok_or(())?.parse(),
+                }
+                .map_err(|_| ())?;
+                char::from_u32(code).filter(|&c| c != '\0').ok_or(())?
+            }
+        };
+        push(c.encode_utf8(&mut [0; 4]));
+        rest = &rest[end + 1..];
+    }
+    push(rest);
+    Ok(())
+}
+
+/// Appends the unescaped text, without allocating unless `out` needs to grow
+fn unescape_into(raw: &str, out: &mut String) -> Result<(), ()> {
+    unescape_with(raw, |s| out.push_str(s))
+}
+
+/// Unescapes text which has already been checked, only allocating if there is anything to unescape
+fn unescape(raw: &str) -> Cow<'_, str> {
+    if !raw.contains('&') {
+        return Cow::Borrowed(raw);
+    }
+    let mut unescaped = String::with_capacity(raw.len());
+    match unescape_into(raw, &mut unescaped) {
+        Ok(()) => Cow::Owned(unescaped),
+        Err(()) => Cow::Borrowed(raw),
+    }
+}
+
+/// The error for text which `unescape_into` rejected
+fn unescape_error(raw: &str, position: usize) -> Error {
+    let error = match quick_xml::escape::unescape(raw.as_bytes()) {
+        Err(e) => quick_xml::Error::EscapeError(e),
+        Ok(_) => quick_xml::Error::UnexpectedToken(raw.to_string()),
+    };
+    Error::from_xml(error, position)
+}
 
 pub(crate) struct Traverser {
     buf: Vec<u8>,
+    /// The namespace bindings in scope, kept by the reader
+    ns_buf: Vec<u8>,
     path: ElementPathBuf,
     drop_last: bool,
     current: Option<Node>,
+    /// The content of the current node if it is text, a comment or similar, text is kept escaped until it is needed
+    content: String,
 }
 
 impl Traverser {
     pub(crate) fn new() -> Traverser {
         Self {
             buf: vec![],
+            ns_buf: vec![],
             path: ElementPathBuf::new(),
             drop_last: false,
             current: None,
+            content: String::new(),
         }
     }
 
-    pub(crate) fn read_from<B: std::io::BufRead>(&mut self, reader: &mut quick_xml::Reader<B>) {
+    pub(crate) fn set_document_mode(&mut self, mode: DocumentMode) {
+        self.path.html = mode == DocumentMode::Html;
+    }
+
+    pub(crate) fn read_from(&mut self, reader: &mut impl EventReader) -> Result<(), Error> {
         if self.drop_last {
-            self.path.path.pop().unwrap();
+            self.path.pop();
             self.drop_last = false;
         }
-        self.current = match reader.read_event(&mut self.buf) {
-            Ok(e) => match e {
-                Event::Start(start) => Some(self.path.start(start, reader)),
-                Event::End(end) => {
-                    let decode = reader.decode(end.name()).unwrap();
-                    let element = self.path.path.last().unwrap();
-                    self.drop_last = true;
-                    assert_eq!(decode, element.name);
-                    Some(self.path.end())
-                }
-                Event::Empty(_) => todo!(),
-                Event::Text(text) => {
-                    Some(self.path.text(text.unescape_and_decode(reader).unwrap()))
-                }
-                Event::Comment(_) => todo!(),
-                Event::CData(_) => todo!(),
-                Event::Decl(_) => todo!(),
-                Event::PI(_) => todo!(),
-                Event::DocType(text) => {
-                    Some(self.path.doctype(text.unescape_and_decode(reader).unwrap()))
+        self.current = None;
+        self.buf.clear();
+        self.content.clear();
+        let (namespace, event) = reader
+            .read_namespaced_event(&mut self.buf, &mut self.ns_buf)
+            .map_err(|e| Error::from_xml(e, reader.buffer_position()))?;
+        let position = reader.buffer_position();
+        let content = &mut self.content;
+        let mut set_content = |bytes: &[u8]| -> Result<(), Error> {
+            content.push_str(decode(bytes, position)?);
+            Ok(())
+        };
+        self.current = match event {
+            Event::Start(start) => Some(self.path.start(start, namespace, position)?),
+            Event::End(end) => {
+                let found =
+                    str::from_utf8(end.name()).map_err(|e| Error::from_xml(e.into(), position))?;
+                let path = &self.path;
+                let expected = path.path.last().map(|e| path.str(e.name));
+                if !expected.map_or(false, |expected| names_eq(path.html, expected, found)) {
+                    return Err(Error::MismatchedEndTag {
+                        expected: expected.unwrap_or_default().to_string(),
+                        found: found.to_string(),
+                        position,
+                    });
                 }
-                Event::Eof => None,
-            },
-            Err(e) => panic!("{}", e),
-        }
+                self.drop_last = true;
+                Some(self.path.end())
+            }
+            Event::Empty(start) => {
+                let node = self.path.empty(start, namespace, position)?;
+                self.drop_last = true;
+                Some(node)
+            }
+            Event::Text(text) if reader.raw_text() => {
+                set_content(text.escaped())?;
+                Some(Node::RawText)
+            }
+            Event::Text(text) => {
+                let raw = decode(text.escaped(), position)?;
+                // checked now so that unescaping can be left until the text is needed
+                unescape_with(raw, |_| {}).map_err(|_| unescape_error(raw, position))?;
+                self.content.push_str(raw);
+                Some(self.path.text())
+            }
+            Event::Comment(text) => {
+                set_content(text.escaped())?;
+                Some(Node::Comment)
+            }
+            Event::CData(text) => {
+                set_content(&text.unescaped().map_err(|e| Error::from_xml(e, position))?)?;
+                Some(Node::CData)
+            }
+            Event::Decl(decl) => {
+                set_content(&decl)?;
+                Some(Node::Decl)
+            }
+            Event::PI(text) => {
+                set_content(text.escaped())?;
+                Some(Node::PI)
+            }
+            Event::DocType(text) => {
+                set_content(text.escaped())?;
+                Some(self.path.doctype())
+            }
+            Event::Eof if !self.path.path.is_empty() => {
+                return Err(Error::UnexpectedEof {
+                    open_elements: self.path.path.len(),
+                    position,
+                })
+            }
+            Event::Eof => None,
+        };
+        Ok(())
     }
 
-    pub fn get(&self) -> Option<Item> {
-        self.current.as_ref().map(|node| Item {
+    pub fn get(&self) -> Option<Item<'_>> {
+        self.current.map(|node| Item {
             context: self.path.as_path(),
-            node: node.clone(),
+            node,
+            content: &self.content,
         })
     }
 }
 
 /// An owned path of elements
+///
+/// The names and attributes of all the elements are stored in shared buffers which are truncated as elements are popped, so once they have grown to the depth of the document no more allocations are needed
 #[derive(Debug, Clone)]
 pub struct ElementPathBuf {
     path: Vec<NormalisedElement>,
+    attrs: Vec<NormalisedAttribute>,
+    /// The names, attribute names and attribute values of the elements in the path
+    strings: String,
+    siblings: Siblings,
+    /// Reused for sorting the attributes of an element
+    attr_order: Vec<usize>,
+    /// The id of the next element pushed
+    next_id: usize,
+    /// Whether names are compared as in html rather than xml
+    html: bool,
+}
+
+/// An element of the path a copy was made from, and whether it's in the copy
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Copied {
+    id: usize,
+    pub(crate) kept: bool,
 }
 
 impl ElementPathBuf {
     pub(crate) fn new() -> Self {
-        Self { path: vec![] }
+        Self {
+            path: vec![],
+            attrs: vec![],
+            strings: String::new(),
+            siblings: Siblings::new(),
+            attr_order: vec![],
+            next_id: 0,
+            html: false,
+        }
     }
 
-    fn text(&self, text: String) -> Node {
-        Node::Text(text)
+    fn text(&self) -> Node {
+        Node::Text
     }
 
-    fn doctype(&self, text: String) -> Node {
-        Node::DocType(text)
+    fn doctype(&self) -> Node {
+        Node::DocType
     }
 
-    fn start<B: BufRead>(&mut self, start: BytesStart, reader: &Reader<B>) -> Node {
-        let element = NormalisedElement {
-            name: reader.decode(start.name()).unwrap().to_string(),
-            attrs: start
-                .attributes()
-                .map(|a| {
-                    let a = a.unwrap();
-                    NormalisedAttribute {
-                        name: reader.decode(a.key).unwrap().to_string(),
-                        value: reader.decode(&a.value).unwrap().to_string(),
+    fn start(
+        &mut self,
+        start: BytesStart,
+        namespace: Option<&[u8]>,
+        position: usize,
+    ) -> Result<Node, Error> {
+        self.push(start, namespace, position)?;
+        Ok(Node::Start)
+    }
+
+    /// A self-closing element, it is pushed onto the path like a start but the caller needs to pop it before the next item
+    fn empty(
+        &mut self,
+        start: BytesStart,
+        namespace: Option<&[u8]>,
+        position: usize,
+    ) -> Result<Node, Error> {
+        self.push(start, namespace, position)?;
+        Ok(Node::Empty)
+    }
+
+    fn push(
+        &mut self,
+        start: BytesStart,
+        namespace: Option<&[u8]>,
+        position: usize,
+    ) -> Result<(), Error> {
+        let (strings_len, attrs_len) = (self.strings.len(), self.attrs.len());
+        let result = self.try_push(start, namespace, position);
+        if result.is_err() {
+            self.strings.truncate(strings_len);
+            self.attrs.truncate(attrs_len);
+        }
+        result
+    }
+
+    fn try_push(
+        &mut self,
+        start: BytesStart,
+        namespace: Option<&[u8]>,
+        position: usize,
+    ) -> Result<(), Error> {
+        let name = self.push_str(start.name(), position)?;
+        let attrs_start = self.attrs.len();
+        for a in start.attributes().with_checks(false) {
+            let a = a.map_err(|e| Error::from_xml(e, position))?;
+            let name = self.push_str(a.key, position)?;
+            let raw = decode(&a.value, position)?;
+            let start = self.strings.len();
+            unescape_into(raw, &mut self.strings).map_err(|_| unescape_error(raw, position))?;
+            let value = Span {
+                start,
+                end: self.strings.len(),
+            };
+            self.attrs.push(NormalisedAttribute { name, value });
+        }
+        if self.has_duplicate_attribute(attrs_start) {
+            return Err(Error::from_xml(
+                quick_xml::Error::DuplicatedAttribute(position, position),
+                position,
+            ));
+        }
+        let namespace = match namespace {
+            Some(b"") => None,
+            Some(uri) => Some(self.push_namespace(uri, position)?),
+            None if self.html => Some(self.html_namespace(name)),
+            None => None,
+        };
+        self.push_element(name, attrs_start, namespace);
+        Ok(())
+    }
+
+    /// Whether any of the attributes from `start` have the same name, they're sorted so that tags with many attributes don't take quadratic time
+    fn has_duplicate_attribute(&mut self, start: usize) -> bool {
+        let (strings, attrs, html) = (&self.strings, &self.attrs[start..], self.html);
+        if attrs.len() < 2 {
+            return false;
+        }
+        let name = |i: &usize| {
+            let span = attrs[*i].name;
+            strings[span.start..span.end]
+                .bytes()
+                .map(move |b| if html { b.to_ascii_lowercase() } else { b })
+        };
+        let order = &mut self.attr_order;
+        order.clear();
+        order.extend(0..attrs.len());
+        order.sort_unstable_by(|a, b| name(a).cmp(name(b)));
+        order
+            .windows(2)
+            .any(|pair| name(&pair[0]).eq(name(&pair[1])))
+    }
+
+    /// Stores a namespace URI, sharing the parent's copy when they are the same as they usually are
+    fn push_namespace(&mut self, uri: &[u8], position: usize) -> Result<Span, Error> {
+        let parent = self.path.last().and_then(|parent| parent.namespace);
+        match parent {
+            Some(span) if self.strings.as_bytes()[span.start..span.end] == *uri => Ok(span),
+            _ => self.push_str(uri, position),
+        }
+    }
+
+    /// The namespace of an element in an html document which wasn't bound to one, `svg` and `math` elements start foreign content which their descendants are in, except inside `foreignObject`
+    fn html_namespace(&mut self, name: Span) -> Span {
+        let name = &self.strings[name.start..name.end];
+        let uri = if name.eq_ignore_ascii_case("svg") {
+            SVG_NAMESPACE
+        } else if name.eq_ignore_ascii_case("math") {
+            MATHML_NAMESPACE
+        } else {
+            match self.path.last() {
+                Some(parent) if !names_eq(true, self.str(parent.name), "foreignObject") => {
+                    if let Some(span) = parent.namespace {
+                        return span;
                     }
-                })
-                .collect(),
+                    XHTML_NAMESPACE
+                }
+                _ => XHTML_NAMESPACE,
+            }
+        };
+        self.push_string(uri)
+    }
+
+    /// Pushes an element whose name and attributes are at the end of the buffers
+    fn push_element(&mut self, name: Span, attrs_start: usize, namespace: Option<Span>) {
+        let depth = self.path.len();
+        let (index, type_index) =
+            self.siblings
+                .start(depth, &self.strings[name.start..name.end], self.html);
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1);
+        self.path.push(NormalisedElement {
+            id,
+            name,
+            attrs: Span {
+                start: attrs_start,
+                end: self.attrs.len(),
+            },
+            namespace,
+            depth,
+            index,
+            type_index,
+        });
+    }
+
+    fn push_str(&mut self, bytes: &[u8], position: usize) -> Result<Span, Error> {
+        let s = str::from_utf8(bytes).map_err(|e| Error::from_xml(e.into(), position))?;
+        Ok(self.push_string(s))
+    }
+
+    fn push_string(&mut self, s: &str) -> Span {
+        let start = self.strings.len();
+        self.strings.push_str(s);
+        Span {
+            start,
+            end: self.strings.len(),
+        }
+    }
+
+    /// Makes this path a rewritten copy of the item's path, only the elements which weren't in the previous item's path are rewritten
+    pub(crate) fn map_from(
+        &mut self,
+        item: &Item<'_>,
+        copied: &mut Vec<Copied>,
+        f: &mut impl MapElement,
+    ) {
+        self.copy_from(item, copied, |start| {
+            Some(f.map_element(start.as_element().unwrap()))
+        })
+    }
+
+    /// Makes this path a copy of the item's path with elements rewritten, or left out where `f` returns `None`
+    ///
+    /// `f` is called with the start of each element which wasn't in the previous item's path, and `copied` keeps each element of the item's path and whether it is in this one.
+    pub(crate) fn copy_from<N: AsRef<str>, E: EditAttributes>(
+        &mut self,
+        item: &Item<'_>,
+        copied: &mut Vec<Copied>,
+        mut f: impl FnMut(Item<'_>) -> Option<ElementMap<N, E>>,
+    ) {
+        let context = item.context;
+        let mut kept = match item.node {
+            // the element is new, though an end tag's element was already copied for its start tag
+            Node::Start | Node::Empty => context.path.len() - 1,
+            _ => context.path.len(),
         };
-        self.path.push(element);
-        Node::Start
+        kept = kept.min(copied.len());
+        // the paths usually only differ at the end, but an iterator before this one may have skipped the start of an element, and the end of one before it at the same depth
+        while kept > 0 && copied[kept - 1].id != context.path[kept - 1].id {
+            kept -= 1;
+        }
+        while copied.len() > kept {
+            if copied.pop().unwrap().kept {
+                self.pop();
+            }
+        }
+        for end in copied.len()..context.path.len() {
+            let start = ElementPath {
+                path: &context.path[..=end],
+                buf: context.buf,
+            };
+            let map = f(start.as_item().unwrap());
+            copied.push(Copied {
+                id: context.path[end].id,
+                kept: map.is_some(),
+            });
+            if let Some(map) = map {
+                self.push_mapped(start.last().unwrap(), map);
+            }
+        }
+    }
+
+    fn push_mapped<N: AsRef<str>, E: EditAttributes>(
+        &mut self,
+        element: Element<'_>,
+        map: ElementMap<N, E>,
+    ) {
+        self.html = element.buf.html;
+        let name = self.push_string(map.name.as_ref().map_or(element.name(), AsRef::as_ref));
+        let namespace = element
+            .namespace()
+            .map(|uri| self.push_namespace(uri.as_bytes(), 0).unwrap());
+        let attrs_start = self.attrs.len();
+        let (strings, attrs) = (&mut self.strings, &mut self.attrs);
+        map.attributes
+            .edit(element.attributes(), &mut |name, value| {
+                let mut push = |s: &str| {
+                    let start = strings.len();
+                    strings.push_str(s);
+                    Span {
+                        start,
+                        end: strings.len(),
+                    }
+                };
+                let attr = NormalisedAttribute {
+                    name: push(name),
+                    value: push(value),
+                };
+                attrs.push(attr);
+            });
+        self.push_element(name, attrs_start, namespace);
+    }
+
+    fn pop(&mut self) {
+        let element = self.path.pop().unwrap();
+        self.siblings.end(&element, &self.strings, &self.attrs);
+        self.strings.truncate(element.name.start);
+        self.attrs.truncate(element.attrs.start);
+    }
+
+    fn str(&self, span: Span) -> &str {
+        &self.strings[span.start..span.end]
     }
 
     #[cfg(test)]
     pub(crate) fn append_element(&mut self, name: &str, attr: Vec<(&str, &str)>) -> &mut Self {
-        let element = NormalisedElement {
-            name: name.to_string(),
-            attrs: attr
-                .into_iter()
-                .map(|(name, value)| NormalisedAttribute {
-                    name: name.to_string(),
-                    value: value.to_string(),
-                })
-                .collect(),
-        };
-        self.path.push(element);
+        let name = self.push_str(name.as_bytes(), 0).unwrap();
+        let attrs_start = self.attrs.len();
+        for (name, value) in attr {
+            let name = self.push_str(name.as_bytes(), 0).unwrap();
+            let value = self.push_str(value.as_bytes(), 0).unwrap();
+            self.attrs.push(NormalisedAttribute { name, value });
+        }
+        self.push_element(name, attrs_start, None);
+        self
+    }
+
+    #[cfg(test)]
+    pub(crate) fn close_element(&mut self) -> &mut Self {
+        self.pop();
         self
     }
 
@@ -121,7 +594,7 @@ impl ElementPathBuf {
         Node::End
     }
 
-    pub(crate) fn as_path(&self) -> ElementPath {
+    pub(crate) fn as_path(&self) -> ElementPath<'_> {
         ElementPath {
             path: &self.path,
             buf: self,
@@ -129,6 +602,192 @@ impl ElementPathBuf {
     }
 }
 
+/// What is kept about the siblings of the open elements
+///
+/// This is proportional to the depth of the path plus the number of distinct names among the children of the open elements, so a document whose elements all have different names keeps all their names. The counts of each name are found by hash, so each start tag takes the same time however many names there are.
+#[derive(Debug, Clone)]
+struct Siblings {
+    /// The children of the document and of each open element, so there is one more than the depth of the path
+    levels: Vec<Level>,
+    /// The number of children with each name so far in each level
+    types: Vec<TypeCount>,
+    type_names: String,
+    /// The last of the types in a level whose names have a hash, keyed by the level's depth and the hash
+    type_hashes: HashMap<(usize, u64), usize>,
+    /// Randomly seeded so that documents can't be made to have names which collide
+    hasher: RandomState,
+    /// The names and attributes of the previous sibling in each level
+    strings: String,
+    attrs: Vec<NormalisedAttribute>,
+}
+
+/// The children seen so far of the document or of an open element
+#[derive(Debug, Clone, Copy)]
+struct Level {
+    children: usize,
+    /// The starts of this level's parts of the sibling buffers
+    types: usize,
+    type_names: usize,
+    strings: usize,
+    attrs: usize,
+    /// The last child which has ended, stored in the sibling buffers
+    previous: Option<NormalisedElement>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TypeCount {
+    name: Span,
+    count: usize,
+    hash: u64,
+    /// The previous type in the level whose name has the same hash
+    collision: Option<usize>,
+}
+
+impl Siblings {
+    fn new() -> Self {
+        let mut siblings = Siblings {
+            levels: vec![],
+            types: vec![],
+            type_names: String::new(),
+            type_hashes: HashMap::new(),
+            hasher: RandomState::new(),
+            strings: String::new(),
+            attrs: vec![],
+        };
+        siblings.push_level();
+        siblings
+    }
+
+    fn push_level(&mut self) {
+        self.levels.push(Level {
+            children: 0,
+            types: self.types.len(),
+            type_names: self.type_names.len(),
+            strings: self.strings.len(),
+            attrs: self.attrs.len(),
+            previous: None,
+        });
+    }
+
+    /// Hashes a name so that names which are equal in the document's mode have the same hash
+    fn hash(&self, name: &str, html: bool) -> u64 {
+        let mut hasher = self.hasher.build_hasher();
+        if html {
+            for byte in name.bytes() {
+                hasher.write_u8(byte.to_ascii_lowercase());
+            }
+        } else {
+            hasher.write(name.as_bytes());
+        }
+        hasher.finish()
+    }
+
+    /// The type in the level at this depth with the name, and the last type with the name's hash
+    fn find_type(
+        &self,
+        depth: usize,
+        name: &str,
+        hash: u64,
+        html: bool,
+    ) -> (Option<usize>, Option<usize>) {
+        let last = self.type_hashes.get(&(depth, hash)).copied();
+        let mut next = last;
+        while let Some(index) = next {
+            let type_count = &self.types[index];
+            if names_eq(
+                html,
+                &self.type_names[type_count.name.start..type_count.name.end],
+                name,
+            ) {
+                return (Some(index), last);
+            }
+            next = type_count.collision;
+        }
+        (None, last)
+    }
+
+    /// Counts a child starting in the level at this depth, returning its index among all the children and among those with the same name
+    fn start(&mut self, depth: usize, name: &str, html: bool) -> (usize, usize) {
+        let level = &mut self.levels[depth];
+        let index = level.children;
+        level.children += 1;
+        let hash = self.hash(name, html);
+        let type_index = match self.find_type(depth, name, hash, html) {
+            (Some(found), _) => {
+                let type_count = &mut self.types[found];
+                type_count.count += 1;
+                type_count.count - 1
+            }
+            (None, collision) => {
+                let start = self.type_names.len();
+                self.type_names.push_str(name);
+                self.type_hashes.insert((depth, hash), self.types.len());
+                self.types.push(TypeCount {
+                    name: Span {
+                        start,
+                        end: self.type_names.len(),
+                    },
+                    count: 1,
+                    hash,
+                    collision,
+                });
+                0
+            }
+        };
+        self.push_level();
+        (index, type_index)
+    }
+
+    /// Drops the level of the ending element's children, and keeps the element as the previous sibling in its own level
+    fn end(&mut self, element: &NormalisedElement, strings: &str, attrs: &[NormalisedAttribute]) {
+        let children = self.levels.pop().unwrap();
+        for type_count in &self.types[children.types..] {
+            self.type_hashes
+                .remove(&(element.depth + 1, type_count.hash));
+        }
+        self.types.truncate(children.types);
+        self.type_names.truncate(children.type_names);
+        let level = self.levels.last_mut().unwrap();
+        self.strings.truncate(level.strings);
+        self.attrs.truncate(level.attrs);
+        let sibling_strings = &mut self.strings;
+        let mut push_str = |span: Span| {
+            let start = sibling_strings.len();
+            sibling_strings.push_str(&strings[span.start..span.end]);
+            Span {
+                start,
+                end: sibling_strings.len(),
+            }
+        };
+        let name = push_str(element.name);
+        let namespace = element.namespace.map(&mut push_str);
+        for attr in &attrs[element.attrs.start..element.attrs.end] {
+            let attr = NormalisedAttribute {
+                name: push_str(attr.name),
+                value: push_str(attr.value),
+            };
+            self.attrs.push(attr);
+        }
+        level.previous = Some(NormalisedElement {
+            name,
+            namespace,
+            attrs: Span {
+                start: level.attrs,
+                end: self.attrs.len(),
+            },
+            ..*element
+        });
+    }
+
+    /// The number of children in the level at this depth with the name so far
+    fn count(&self, depth: usize, name: &str, html: bool) -> usize {
+        let hash = self.hash(name, html);
+        self.find_type(depth, name, hash, html)
+            .0
+            .map_or(0, |index| self.types[index].count)
+    }
+}
+
 /// A path of elements
 #[derive(Clone, Copy)]
 pub struct ElementPath<'a> {
@@ -142,7 +801,8 @@ impl<'a> ElementPath<'a> {
             Some((
                 Element {
                     element,
-                    _buf: self.buf,
+                    buf: self.buf,
+                    sibling: false,
                 },
                 ElementPath {
                     path,
@@ -159,23 +819,45 @@ impl<'a> ElementPath<'a> {
             Some(Item {
                 context: *self,
                 node: Node::Start,
+                content: "",
             })
         } else {
             None
         }
     }
 
+    /// The number of elements in the path
+    pub fn depth(&self) -> usize {
+        self.path.len()
+    }
+
+    /// The outermost element
+    pub fn first(&self) -> Option<Element<'a>> {
+        self.path.first().map(|element| self.as_element(element))
+    }
+
+    /// The innermost element
+    pub fn last(&self) -> Option<Element<'a>> {
+        self.path.last().map(|element| self.as_element(element))
+    }
+
+    /// The elements from the outermost, `.iter().rev()` goes from the innermost
+    pub fn iter(&self) -> ElementPathIter<'a> {
+        self.into_iter()
+    }
+
     fn as_element(&self, first: &'a NormalisedElement) -> Element<'a> {
         Element {
             element: first,
-            _buf: self.buf,
+            buf: self.buf,
+            sibling: false,
         }
     }
 }
 
 impl<'a> fmt::Debug for ElementPath<'a> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        for element in self.path {
+        for element in *self {
             write!(f, "/{:?}", element)?;
         }
         Ok(())
@@ -218,40 +900,49 @@ impl<'a> DoubleEndedIterator for ElementPathIter<'a> {
     }
 }
 
-/// Currently Heap allocated, but to be fixed size with no references, instead should only contain slice index ranges into vecs stored on element paths
-#[derive(Clone)]
-struct NormalisedElement {
-    name: String,
-    attrs: Vec<NormalisedAttribute>,
+/// A range of the strings buffer on an `ElementPathBuf`, or of its attributes
+#[derive(Clone, Copy, Debug)]
+struct Span {
+    start: usize,
+    end: usize,
 }
 
-impl fmt::Debug for NormalisedElement {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        f.write_str(&self.name)?;
-        for a in &self.attrs {
-            write!(f, " {}=\"{}\"", a.name, a.value)?;
-        }
-        Ok(())
-    }
+/// Fixed size, the name and attributes are stored in the `ElementPathBuf`
+#[derive(Clone, Copy, Debug)]
+struct NormalisedElement {
+    /// Different from the elements pushed before it, so that a copy of the path can tell whether it's the element it copied
+    id: usize,
+    name: Span,
+    attrs: Span,
+    /// The URI of the namespace the name is bound to, if any
+    namespace: Option<Span>,
+    /// The position in the path, which is also the level of its siblings
+    depth: usize,
+    /// The position among its element siblings, and among those with the same name
+    index: usize,
+    type_index: usize,
 }
 
-/// Currently Heap allocated, but to be fixed size with no references, instead should only contain slice index ranges into vecs stored on element paths
-#[derive(Clone, Debug)]
-pub(crate) struct NormalisedAttribute {
-    pub(crate) name: String,
-    pub(crate) value: String,
+/// Fixed size, the name and value are stored in the `ElementPathBuf`
+#[derive(Clone, Copy, Debug)]
+struct NormalisedAttribute {
+    name: Span,
+    value: Span,
 }
 
 /// An item in the traversal, with access to the current node and the context of elements
+#[derive(Clone, Copy)]
 pub struct Item<'a> {
     context: ElementPath<'a>,
     node: Node,
+    /// The content of text and other non-element nodes, text is still escaped
+    content: &'a str,
 }
 
 impl<'a> Item<'a> {
     pub fn as_element(&self) -> Option<Element<'a>> {
         match self.node {
-            Node::Start | Node::End => self
+            Node::Start | Node::End | Node::Empty => self
                 .context
                 .path
                 .last()
@@ -261,9 +952,9 @@ impl<'a> Item<'a> {
     }
 
     /// The element path, not including the potential current element
-    pub(crate) fn into_context_path(self) -> ElementPath<'a> {
+    pub fn into_context_path(self) -> ElementPath<'a> {
         match self.node {
-            Node::Start | Node::End => ElementPath {
+            Node::Start | Node::End | Node::Empty => ElementPath {
                 path: &self.context.path[0..(self.context.path.len() - 1)],
                 buf: self.context.buf,
             },
@@ -272,47 +963,129 @@ impl<'a> Item<'a> {
     }
 
     /// The element path, not including the potential current element
-    pub(crate) fn context_path(&self) -> ElementPath<'_> {
+    pub fn context_path(&self) -> ElementPath<'a> {
+        self.into_context_path()
+    }
+
+    /// The name of the element, if this item is an element's start, end or a self-closing element
+    pub fn name(&self) -> Option<&'a str> {
+        self.as_element().map(|element| element.name())
+    }
+
+    /// The value of the element's attribute, if this item is an element's start or a self-closing element, its name ignores ASCII case in html documents
+    pub fn attr(&self, name: &str) -> Option<&'a str> {
         match self.node {
-            Node::Start | Node::End => ElementPath {
-                path: &self.context.path[0..(self.context.path.len() - 1)],
-                buf: self.context.buf,
-            },
-            _ => self.context,
+            Node::Start | Node::Empty => self.as_element()?.attr(name),
+            _ => None,
         }
     }
 
+    /// The innermost element containing the item, which is the parent of an element
+    pub fn first_ancestor(&self) -> Option<Element<'a>> {
+        self.into_context_path().last()
+    }
+
+    /// The number of elements in the path, including the element itself if it is one, so the root element and the text directly in it are at depth 1
+    pub fn depth(&self) -> usize {
+        self.context.depth()
+    }
+
     /// The element path, including the element itself if it is one
-    pub fn as_path(&self) -> ElementPath {
+    pub fn as_path(&self) -> ElementPath<'a> {
         self.context
     }
 
-    pub fn as_event(&self) -> Event<'static> {
-        use std::fmt::Write;
-
+    /// The item as an xml event, borrowing any text content without unescaping it
+    pub fn as_event(&self) -> Event<'a> {
+        let content = self.content;
         match self.node {
-            Node::Text(ref unescaped) => {
-                let bytes_text = BytesText::from_escaped_str(unescaped).into_owned();
-                Event::Text(bytes_text)
-            }
-            Node::DocType(ref text) => {
-                Event::DocType(BytesText::from_escaped_str(text).into_owned())
-            }
-            Node::Start => {
-                let element = self.context.path.last().unwrap();
-                let mut s = element.name.clone();
-                let name_len = s.len();
-                for NormalisedAttribute { name, value } in &element.attrs {
-                    write!(&mut s, r#" {}="{}""#, name, value).unwrap();
-                }
-                Event::Start(BytesStart::owned(s, name_len))
-            }
-            Node::End => Event::End(BytesEnd::owned(
-                self.context.path.last().unwrap().name.clone().into_bytes(),
+            Node::Text | Node::RawText => Event::Text(BytesText::from_escaped_str(content)),
+            Node::DocType => Event::DocType(BytesText::from_escaped_str(content)),
+            Node::Comment => Event::Comment(BytesText::from_escaped_str(content)),
+            Node::CData => Event::CData(BytesText::from_escaped_str(content)),
+            Node::Decl => Event::Decl(BytesDecl::from_start(BytesStart::borrowed(
+                content.as_bytes(),
+                content.find(char::is_whitespace).unwrap_or(content.len()),
+            ))),
+            Node::PI => Event::PI(BytesText::from_escaped_str(content)),
+            Node::Start => Event::Start(self.start_tag()),
+            Node::Empty => Event::Empty(self.start_tag()),
+            Node::End => Event::End(BytesEnd::borrowed(
+                self.as_element().unwrap().name().as_bytes(),
             )),
         }
     }
 
+    pub(crate) fn node(&self) -> Node {
+        self.node
+    }
+
+    /// The item with another path of elements, which needs to be a rewritten copy of its own
+    pub(crate) fn with_context(self, context: ElementPath<'a>) -> Item<'a> {
+        Item { context, ..self }
+    }
+
+    /// The content of a non-element node as it was read, text is still escaped
+    pub(crate) fn content(&self) -> &'a str {
+        self.content
+    }
+
+    /// The text, if this item is text, it is only unescaped (and allocated) if it contains character references
+    pub fn text(&self) -> Option<Cow<'a, str>> {
+        match self.node {
+            Node::Text => Some(unescape(self.content)),
+            Node::RawText => Some(Cow::Borrowed(self.content)),
+            _ => None,
+        }
+    }
+
+    /// The content of the comment, if this item is a comment
+    pub fn comment(&self) -> Option<&'a str> {
+        match self.node {
+            Node::Comment => Some(self.content),
+            _ => None,
+        }
+    }
+
+    /// The content of the CDATA section, if this item is one
+    pub fn cdata(&self) -> Option<&'a str> {
+        match self.node {
+            Node::CData => Some(self.content),
+            _ => None,
+        }
+    }
+
+    /// The content of the processing instruction, including its target, if this item is one
+    pub fn processing_instruction(&self) -> Option<&'a str> {
+        match self.node {
+            Node::PI => Some(self.content),
+            _ => None,
+        }
+    }
+
+    /// The content of the XML declaration (eg. `xml version="1.0"`), if this item is one
+    pub fn xml_decl(&self) -> Option<&'a str> {
+        match self.node {
+            Node::Decl => Some(self.content),
+            _ => None,
+        }
+    }
+
+    fn start_tag(&self) -> BytesStart<'a> {
+        use std::fmt::Write;
+
+        let element = self.as_element().unwrap();
+        if element.attributes().next().is_none() {
+            return BytesStart::borrowed_name(element.name().as_bytes());
+        }
+        let mut s = element.name().to_string();
+        let name_len = s.len();
+        for (name, value) in element.attributes() {
+            write!(&mut s, r#" {}="{}""#, name, escape(value, true)).unwrap();
+        }
+        BytesStart::owned(s, name_len)
+    }
+
     pub fn include(self, selector: &dyn ContextualSelector) -> Option<Item<'a>> {
         for start in 0..self.context.path.len() {
             let item = Item {
@@ -321,73 +1094,224 @@ impl<'a> Item<'a> {
                     buf: self.context.buf,
                 },
                 node: Node::Start,
+                content: "",
             };
             if selector.context_match(&item) {
-                let item = Item {
-                    context: ElementPath {
-                        path: &self.context.path[start..],
-                        buf: self.context.buf,
-                    },
-                    node: self.node.clone(),
-                };
-                return Some(item);
+                return Some(self.strip_ancestors(start));
             }
         }
         None
     }
+
+    /// The item without the outermost elements of its path
+    pub(crate) fn strip_ancestors(self, count: usize) -> Item<'a> {
+        Item {
+            context: ElementPath {
+                path: &self.context.path[count..],
+                buf: self.context.buf,
+            },
+            ..self
+        }
+    }
+}
+
+/// Writes the item with xml syntax as `as_event` gives it, use `HtmlWriter` to balance the end tags or to write html
+impl<'a> fmt::Display for Item<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let content = self.content;
+        match self.node {
+            Node::Start | Node::Empty => {
+                let element = self.as_element().unwrap();
+                write!(f, "<{}", element.name())?;
+                for (name, value) in element.attributes() {
+                    write!(f, r#" {}="{}""#, name, escape(value, true))?;
+                }
+                f.write_str(if let Node::Empty = self.node {
+                    "/>"
+                } else {
+                    ">"
+                })
+            }
+            Node::End => write!(f, "</{}>", self.as_element().unwrap().name()),
+            Node::Text | Node::RawText => f.write_str(content),
+            Node::DocType => write!(f, "<!DOCTYPE{}>", content),
+            Node::Comment => write!(f, "<!--{}-->", content),
+            Node::CData => write!(f, "<![CDATA[{}]]>", content),
+            Node::Decl | Node::PI => write!(f, "<?{}?>", content),
+        }
+    }
 }
 
 impl<'a> std::fmt::Debug for Item<'a> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{:?}/{:?}", self.context, self.node)
+        write!(f, "{:?}/", self.context)?;
+        let content = self.content;
+        match self.node {
+            Node::DocType => write!(f, "DOCTYPE {}", content),
+            Node::Start => write!(f, "Start"),
+            Node::End => write!(f, "End"),
+            Node::Empty => write!(f, "Empty"),
+            Node::Text | Node::RawText => fmt::Debug::fmt(content, f),
+            Node::Comment => write!(f, "<!--{}-->", content),
+            Node::CData => write!(f, "<![CDATA[{}]]>", content),
+            Node::Decl | Node::PI => write!(f, "<?{}?>", content),
+        }
     }
 }
 
-// should index elements and unescaped text in the path
-#[derive(Clone)]
-enum Node {
-    DocType(String),
+/// The kind of node an item is, its content is stored alongside it in the item
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum Node {
+    DocType,
     Start,
     End,
-    Text(String),
-}
-
-impl fmt::Debug for Node {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match self {
-            Self::DocType(arg) => write!(f, "DOCTYPE {}", arg),
-            Self::Start => write!(f, "Start"),
-            Self::End => write!(f, "End"),
-            Self::Text(arg) => fmt::Debug::fmt(&arg, f),
-        }
-    }
+    /// A self-closing element, which is both the start and the end
+    Empty,
+    /// Escaped text
+    Text,
+    /// The content of a raw text element such as `script`, which isn't escaped when written
+    RawText,
+    /// The raw content of a comment, between `<!--` and `-->`
+    Comment,
+    /// The raw content of a CDATA section, between `<![CDATA[` and `]]>`
+    CData,
+    /// The raw content of an XML declaration, between `<?` and `?>`
+    Decl,
+    /// The raw content of a processing instruction, between `<?` and `?>`
+    PI,
 }
 
 /// An element in the context
+#[derive(Clone, Copy)]
 pub struct Element<'a> {
     element: &'a NormalisedElement,
-    _buf: &'a ElementPathBuf,
+    buf: &'a ElementPathBuf,
+    /// Whether this is the previous sibling of an element in the path, which is kept in the sibling buffers
+    sibling: bool,
 }
 
 impl<'a> Element<'a> {
-    pub(crate) fn name(&self) -> &str {
-        &self.element.name
+    fn strings(&self) -> &'a str {
+        if self.sibling {
+            &self.buf.siblings.strings
+        } else {
+            &self.buf.strings
+        }
+    }
+
+    /// The name as it is in the document, including any namespace prefix
+    pub fn name(&self) -> &'a str {
+        let name = self.element.name;
+        &self.strings()[name.start..name.end]
+    }
+
+    /// The names and values of the attributes, in the order they are in the document
+    pub fn attributes(&self) -> Attributes<'a> {
+        let attrs = if self.sibling {
+            &self.buf.siblings.attrs
+        } else {
+            &self.buf.attrs
+        };
+        Attributes {
+            attrs: attrs[self.element.attrs.start..self.element.attrs.end].iter(),
+            strings: self.strings(),
+            html: self.buf.html,
+        }
     }
 
-    pub(crate) fn attributes(&self) -> std::slice::Iter<'_, NormalisedAttribute> {
-        self.element.attrs.iter()
+    /// The URI of the namespace the element's name is bound to, elements in html documents are in the html, svg or mathml namespace unless they were bound to another
+    pub fn namespace(&self) -> Option<&'a str> {
+        self.element
+            .namespace
+            .map(|uri| &self.strings()[uri.start..uri.end])
     }
 
-    pub fn attr(&self, search: &str) -> Option<&str> {
-        for NormalisedAttribute { name, value } in self.attributes() {
-            if name == search {
+    /// The name without its namespace prefix
+    pub fn local_name(&self) -> &'a str {
+        let name = self.name();
+        name.split_once(':').map_or(name, |(_, local)| local)
+    }
+
+    /// Whether the element is in the namespace and has the local name, ignoring ASCII case in html documents
+    pub fn is_named_in(&self, namespace: &str, local_name: &str) -> bool {
+        self.namespace() == Some(namespace)
+            && names_eq(self.buf.html, self.local_name(), local_name)
+    }
+
+    /// The number of elements in the document's path up to and including this one, so the root element is at depth 1
+    ///
+    /// Ancestors which `include` and `group_under` leave out of the path are counted, while elements removed by `unwrap` aren't, as with `sibling_index`.
+    pub fn depth(&self) -> usize {
+        self.element.depth + 1
+    }
+
+    /// The position among the element's siblings which are elements, counting from 0
+    pub fn sibling_index(&self) -> usize {
+        self.element.index
+    }
+
+    /// The position among the element's siblings with the same name, counting from 0
+    pub fn sibling_index_of_type(&self) -> usize {
+        self.element.type_index
+    }
+
+    /// The sibling element before this one, only the previous sibling of elements in the path is kept so it has no previous sibling itself
+    pub fn previous_sibling(&self) -> Option<Element<'a>> {
+        if self.sibling {
+            return None;
+        }
+        self.buf.siblings.levels[self.element.depth]
+            .previous
+            .as_ref()
+            .map(|element| Element {
+                element,
+                buf: self.buf,
+                sibling: true,
+            })
+    }
+
+    /// Whether any sibling before this element has the name
+    pub fn has_preceding_sibling_named(&self, name: &str) -> bool {
+        let depth = self.element.depth;
+        let html = self.buf.html;
+        let mut count = self.buf.siblings.count(depth, name, html);
+        // the level has counted every sibling up to the element in the path, which is after a previous sibling
+        if self.sibling {
+            count -= names_eq(html, self.buf.str(self.buf.path[depth].name), name) as usize;
+        }
+        count -= self.is_named(name) as usize;
+        count > 0
+    }
+
+    /// Whether the element has the name, ignoring ASCII case in html documents
+    pub fn is_named(&self, name: &str) -> bool {
+        names_eq(self.buf.html, self.name(), name)
+    }
+
+    /// The value of the attribute, its name ignores ASCII case in html documents
+    pub fn attr(&self, search: &str) -> Option<&'a str> {
+        for (name, value) in self.attributes() {
+            if names_eq(self.buf.html, name, search) {
                 return Some(value);
             }
         }
         None
     }
 
-    pub fn classes(&self) -> Classes {
+    /// Rewrites the attributes when it's used with `HtmlIterator::map_all`, eg. `element.map_attributes(|attributes| attributes.filter(|name, _value| name != "id"))`
+    pub fn map_attributes<E: EditAttributes>(
+        self,
+        f: impl FnOnce(Unchanged) -> E,
+    ) -> ElementMap<&'static str, E> {
+        ElementMap::new().map_attributes(f)
+    }
+
+    /// Renames the element when it's used with `HtmlIterator::map_all`
+    pub fn rename<N: AsRef<str>>(self, name: N) -> ElementMap<N, Unchanged> {
+        ElementMap::new().rename(name)
+    }
+
+    pub fn classes(&self) -> Classes<'a> {
         match self.attr("class") {
             Some(s) => Classes { s },
             None => Classes { s: "" },
@@ -395,6 +1319,44 @@ impl<'a> Element<'a> {
     }
 }
 
+impl<'a> fmt::Debug for Element<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())?;
+        for (name, value) in self.attributes() {
+            write!(f, " {}={:?}", name, value)?;
+        }
+        Ok(())
+    }
+}
+
+/// The names and values of an element's attributes
+pub struct Attributes<'a> {
+    attrs: std::slice::Iter<'a, NormalisedAttribute>,
+    strings: &'a str,
+    html: bool,
+}
+
+impl Attributes<'_> {
+    /// Whether the names are compared as in html rather than xml
+    pub(crate) fn html(&self) -> bool {
+        self.html
+    }
+}
+
+impl<'a> Iterator for Attributes<'a> {
+    type Item = (&'a str, &'a str);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let strings = self.strings;
+        self.attrs.next().map(|a| {
+            (
+                &strings[a.name.start..a.name.end],
+                &strings[a.value.start..a.value.end],
+            )
+        })
+    }
+}
+
 pub struct Classes<'a> {
     s: &'a str,
 }
@@ -1,9 +1,23 @@
-use std::io::{self, BufRead, Cursor};
+use std::{
+    cell::RefCell,
+    fmt,
+    io::{self, BufRead, Cursor},
+};
 
+mod error;
+mod html;
 mod iteritem;
+pub mod map;
 pub mod selector;
+#[cfg(feature = "streaming-iterator")]
+pub mod streaming;
 
-use iteritem::{Item, Traverser};
+pub use error::Error;
+use html::HtmlReader;
+pub use iteritem::{Attributes, Classes, Element, ElementPath, ElementPathIter, Item};
+use iteritem::{Copied, ElementPathBuf, EventReader, Node, Traverser};
+use map::{EditAttributes, ElementMap, MapElement};
+use quick_xml::events::Event;
 use selector::ContextualSelector;
 
 pub struct HtmlItem {}
@@ -11,10 +25,23 @@ pub struct HtmlItem {}
 pub trait HtmlIterator {
     fn next(&mut self) -> Option<Item<'_>> {
         self.advance();
-        self.get()
+        Self::get(self)
     }
 
-    fn advance(&mut self);
+    /// Like `next`, but returns the error rather than panicking when the document can't be read
+    fn try_next(&mut self) -> Result<Option<Item<'_>>, Error> {
+        self.try_advance()?;
+        Ok(Self::get(self))
+    }
+
+    /// Panics if the document can't be read, use `try_advance` to handle errors
+    fn advance(&mut self) {
+        if let Err(error) = self.try_advance() {
+            panic!("{}", error)
+        }
+    }
+
+    fn try_advance(&mut self) -> Result<(), Error>;
 
     fn get(&self) -> Option<Item<'_>>;
 
@@ -38,58 +65,387 @@ pub trait HtmlIterator {
         }
     }
 
-    fn write_into(mut self, f: impl io::Write)
+    /// Splits the items into a group for each element the selector matches, with the element's start, its content and its end, and without the elements above it in their paths
+    ///
+    /// Items outside the matched elements are skipped, as are matches inside a group's element. A group which isn't read to its end skips to it when it's dropped.
+    fn group_under<S: ContextualSelector>(self, selector: S) -> GroupUnder<Self, S>
     where
         Self: Sized,
     {
-        let mut writer = HtmlWriter::from_writer(f);
-        while let Some(item) = self.next() {
-            writer.write_item(item)
+        GroupUnder {
+            inner: self,
+            selector,
+            group: None,
+            error: None,
         }
     }
 
-    fn to_string(self) -> String
+    /// Calls the closure with each item, like `Iterator::map`
+    fn map<T, F: FnMut(Item<'_>) -> T>(self, f: F) -> Map<Self, F>
+    where
+        Self: Sized,
+    {
+        Map { inner: self, f }
+    }
+
+    /// Calls the closure with each item, keeping the values it returns, like `Iterator::filter_map`
+    fn filter_map<T, F: FnMut(Item<'_>) -> Option<T>>(self, f: F) -> FilterMap<Self, F>
+    where
+        Self: Sized,
+    {
+        FilterMap { inner: self, f }
+    }
+
+    /// Removes the start and end of the elements the selector matches, keeping their content, and leaves them out of the paths of the items after them
+    fn unwrap<S: ContextualSelector>(self, selector: S) -> Unwrap<Self, S>
+    where
+        Self: Sized,
+    {
+        Unwrap {
+            inner: self,
+            selector,
+            path: ElementPathBuf::new(),
+            copied: vec![],
+        }
+    }
+
+    /// Rewrites every element in the items' paths, so that selectors after it and the written document see the elements as `Element::map_attributes` or `Element::rename` changed them
+    fn map_all<F, N, E>(self, f: F) -> MapAll<Self, F>
+    where
+        Self: Sized,
+        F: FnMut(Element<'_>) -> ElementMap<N, E>,
+        N: AsRef<str>,
+        E: EditAttributes,
+    {
+        MapAll {
+            inner: self,
+            f,
+            path: ElementPathBuf::new(),
+            copied: vec![],
+        }
+    }
+
+    /// Writes all the remaining items, errors reading the document are returned as `io::Error`s wrapping the `Error`
+    fn write_into(self, f: impl io::Write) -> io::Result<()>
+    where
+        Self: Sized,
+    {
+        self.write_with(&mut HtmlWriter::from_writer(f))
+    }
+
+    /// Writes all the remaining items with a configured writer
+    fn write_with<W: io::Write>(mut self, writer: &mut HtmlWriter<W>) -> io::Result<()>
+    where
+        Self: Sized,
+    {
+        while let Some(item) = self.try_next()? {
+            writer.write_item(item)?;
+        }
+        writer.finish()
+    }
+
+    fn try_to_string(self) -> io::Result<String>
     where
         Self: Sized,
     {
         let mut buf = vec![];
-        self.write_into(Cursor::new(&mut buf));
-        String::from_utf8(buf).unwrap()
+        self.write_into(Cursor::new(&mut buf))?;
+        String::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Formats the remaining items once, as `write_into` writes them, into a `fmt::Write` or with `write!` into an `io::Write`
+    ///
+    /// Formatting it again writes nothing, and errors reading the document are `fmt::Error`s, use `write_into` to handle them.
+    fn display(self) -> DisplayOnce<Self>
+    where
+        Self: Sized,
+    {
+        DisplayOnce(RefCell::new(Some(self)))
+    }
+
+    /// Panics if the document can't be read, use `try_to_string` to handle errors
+    fn to_string(self) -> String
+    where
+        Self: Sized,
+    {
+        self.try_to_string().unwrap()
+    }
+
+    /// Wraps the iterator in a `StreamingIterator` over its items, for its combinators
+    #[cfg(feature = "streaming-iterator")]
+    fn streaming(self) -> streaming::Streaming<Self>
+    where
+        Self: Sized,
+    {
+        streaming::Streaming::new(self)
+    }
+}
+
+/// Lets a borrowed iterator be adapted or written, such as a group inside `GroupUnder::map`, reading the items it uses from the iterator
+impl<I: HtmlIterator + ?Sized> HtmlIterator for &mut I {
+    fn advance(&mut self) {
+        (**self).advance()
+    }
+
+    fn try_advance(&mut self) -> Result<(), Error> {
+        (**self).try_advance()
+    }
+
+    fn get(&self) -> Option<Item<'_>> {
+        (**self).get()
+    }
+}
+
+/// Formats an iterator's items once, made with `HtmlIterator::display`
+pub struct DisplayOnce<I>(RefCell<Option<I>>);
+
+impl<I: HtmlIterator> fmt::Display for DisplayOnce<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.borrow_mut().take() {
+            Some(iter) => iter.write_into(FmtWriter(f)).map_err(|_| fmt::Error),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Writes the bytes `HtmlWriter` writes, which are always whole strings, into a formatter
+struct FmtWriter<'f, 'g>(&'f mut fmt::Formatter<'g>);
+
+impl io::Write for FmtWriter<'_, '_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let s =
+            std::str::from_utf8(buf).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        self.0
+            .write_str(s)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
     }
 }
 
+/// Writes items, keeping track of the open elements so that the output is always balanced
+///
+/// End tags which don't close an open element are dropped, and end tags are inserted for open elements which are closed by an ancestor's end tag or by `finish`. In strict mode these are errors instead.
 pub struct HtmlWriter<W: io::Write> {
     inner: quick_xml::Writer<W>,
+    html: bool,
+    minimise_boolean_attributes: bool,
+    strict: bool,
+    /// The start of each open element's name in `open_names`, which has the names one after another
+    open: Vec<usize>,
+    open_names: String,
 }
 
 impl<W: io::Write> HtmlWriter<W> {
+    /// Writes items with xml syntax
     pub fn from_writer(writer: W) -> Self {
         Self {
             inner: quick_xml::Writer::new(writer),
+            html: false,
+            minimise_boolean_attributes: false,
+            strict: false,
+            open: vec![],
+            open_names: String::new(),
         }
     }
 
-    pub fn write_item(&mut self, item: Item) {
-        self.inner.write_event(&item.as_event()).unwrap();
+    /// Writes items with html 5 syntax, void elements have no end tag, no elements are self-closing outside of svg and mathml, and text is escaped as html
+    pub fn from_html_writer(writer: W) -> Self {
+        Self {
+            html: true,
+            ..Self::from_writer(writer)
+        }
+    }
+
+    /// Writes boolean attributes such as `checked` without a value when writing html, defaults to false
+    pub fn minimise_boolean_attributes(&mut self, val: bool) -> &mut Self {
+        self.minimise_boolean_attributes = val;
+        self
+    }
+
+    /// Returns an error for unbalanced items rather than repairing the output, defaults to false
+    pub fn strict(&mut self, val: bool) -> &mut Self {
+        self.strict = val;
+        self
+    }
+
+    pub fn write_item(&mut self, item: Item) -> io::Result<()> {
+        match item.node() {
+            Node::Start => {
+                let name = item.as_element().unwrap().name();
+                if !(self.html && html::is_void(name)) {
+                    self.open.push(self.open_names.len());
+                    self.open_names.push_str(name);
+                }
+            }
+            Node::End => {
+                let element = item.as_element().unwrap();
+                let name = element.name();
+                if self.html && html::is_void(name) {
+                    return Ok(());
+                }
+                match (0..self.open.len()).rposition(|index| self.open_name(index) == name) {
+                    Some(index) => {
+                        if index + 1 < self.open.len() && self.strict {
+                            return Err(unbalanced(format!(
+                                "</{}> closes the unclosed <{}>",
+                                name,
+                                self.open_name(self.open.len() - 1)
+                            )));
+                        }
+                        self.close_to(index + 1)?;
+                        self.open_names.truncate(self.open.pop().unwrap());
+                    }
+                    None if self.strict => {
+                        return Err(unbalanced(format!("</{}> has no start tag", name)))
+                    }
+                    None => return Ok(()),
+                }
+            }
+            _ => {}
+        }
+        if self.html {
+            return html::write_item(self.inner.inner(), &item, self.minimise_boolean_attributes);
+        }
+        match item.node() {
+            // written from the item rather than as an event, which would need the tag in one buffer
+            Node::Start | Node::Empty => write!(self.inner.inner(), "{}", item),
+            _ => self.write_event(item.as_event()),
+        }
     }
+
+    /// Closes all the open elements, in strict mode it is an error if there are any
+    pub fn finish(&mut self) -> io::Result<()> {
+        if self.strict && !self.open.is_empty() {
+            return Err(unbalanced(format!(
+                "<{}> is never closed",
+                self.open_name(self.open.len() - 1)
+            )));
+        }
+        self.close_to(0)
+    }
+
+    fn open_name(&self, index: usize) -> &str {
+        let end = self.open.get(index + 1).copied();
+        &self.open_names[self.open[index]..end.unwrap_or(self.open_names.len())]
+    }
+
+    /// Writes end tags for the open elements from the innermost until there are `len` open
+    fn close_to(&mut self, len: usize) -> io::Result<()> {
+        while self.open.len() > len {
+            let start = self.open.pop().unwrap();
+            write!(self.inner.inner(), "</{}>", &self.open_names[start..])?;
+            self.open_names.truncate(start);
+        }
+        Ok(())
+    }
+
+    fn write_event(&mut self, event: Event) -> io::Result<()> {
+        self.inner.write_event(event).map_err(|e| match e {
+            quick_xml::Error::Io(e) => e,
+            e => io::Error::new(io::ErrorKind::Other, e),
+        })
+    }
+}
+
+fn unbalanced(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// How element and attribute names in a document are compared
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocumentMode {
+    /// Names are case-sensitive
+    Xml,
+    /// Names are ASCII case-insensitive, they are left as they are in the document but selectors and end tags match them in any case
+    Html,
 }
 
+/// The namespace of html elements, which elements in html documents are in unless they are inside `svg` or `math`
+pub const XHTML_NAMESPACE: &str = "http://www.w3.org/1999/xhtml";
+/// The namespace of `svg` elements and their descendants in html documents
+pub const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+/// The namespace of `math` elements and their descendants in html documents
+pub const MATHML_NAMESPACE: &str = "http://www.w3.org/1998/Math/MathML";
+
 pub struct HtmlIter<B: BufRead> {
-    reader: quick_xml::Reader<B>,
+    reader: Tokenizer<B>,
     buf: Traverser,
 }
 
 impl<B: BufRead> HtmlIter<B> {
+    /// Reads a well-formed xml document, in xml mode
     pub fn from_reader(reader: B) -> Self {
         Self {
-            reader: quick_xml::Reader::from_reader(reader),
+            reader: Tokenizer::Xml(quick_xml::Reader::from_reader(reader)),
             buf: Traverser::new(),
         }
     }
+
+    /// Reads an html document leniently, in html mode, void elements, unquoted attributes and missing or stray end tags are handled as an html 5 parser would
+    pub fn from_html_reader(reader: B) -> Self {
+        Self {
+            reader: Tokenizer::Html(HtmlReader::from_reader(reader)),
+            buf: Traverser::new(),
+        }
+        .document_mode(DocumentMode::Html)
+    }
+
+    /// Sets how names are compared, for example to read well-formed html with `from_reader` but match names in any case
+    pub fn document_mode(mut self, mode: DocumentMode) -> Self {
+        if let Tokenizer::Xml(reader) = &mut self.reader {
+            // end tags are checked by the traverser instead
+            reader.check_end_names(mode == DocumentMode::Xml);
+        }
+        self.buf.set_document_mode(mode);
+        self
+    }
+}
+
+enum Tokenizer<B: BufRead> {
+    Xml(quick_xml::Reader<B>),
+    Html(HtmlReader<B>),
+}
+
+impl<B: BufRead> EventReader for Tokenizer<B> {
+    fn read_event<'b>(&mut self, buf: &'b mut Vec<u8>) -> quick_xml::Result<Event<'b>> {
+        match self {
+            Tokenizer::Xml(reader) => EventReader::read_event(reader, buf),
+            Tokenizer::Html(reader) => reader.read_event(buf),
+        }
+    }
+
+    fn read_namespaced_event<'b, 'c>(
+        &mut self,
+        buf: &'b mut Vec<u8>,
+        ns_buf: &'c mut Vec<u8>,
+    ) -> quick_xml::Result<(Option<&'c [u8]>, Event<'b>)> {
+        match self {
+            Tokenizer::Xml(reader) => EventReader::read_namespaced_event(reader, buf, ns_buf),
+            Tokenizer::Html(reader) => reader.read_namespaced_event(buf, ns_buf),
+        }
+    }
+
+    fn buffer_position(&self) -> usize {
+        match self {
+            Tokenizer::Xml(reader) => reader.buffer_position(),
+            Tokenizer::Html(reader) => reader.buffer_position(),
+        }
+    }
+
+    fn raw_text(&self) -> bool {
+        match self {
+            Tokenizer::Xml(reader) => reader.raw_text(),
+            Tokenizer::Html(reader) => reader.raw_text(),
+        }
+    }
 }
 
 impl<B: io::BufRead> HtmlIterator for HtmlIter<B> {
-    fn advance(&mut self) {
+    fn try_advance(&mut self) -> Result<(), Error> {
         self.buf.read_from(&mut self.reader)
     }
     fn get(&self) -> Option<Item<'_>> {
@@ -103,15 +459,14 @@ pub struct Exclude<I, S> {
 }
 
 impl<I: HtmlIterator, S: ContextualSelector> HtmlIterator for Exclude<I, S> {
-    fn advance(&mut self) {
-        while let Some(item) = self.inner.next() {
+    fn try_advance(&mut self) -> Result<(), Error> {
+        while let Some(item) = self.inner.try_next()? {
             if !self.selector.match_any(item.as_path()) {
                 // if nothing in the item's path matches
-                return;
-            } else {
-                drop(item)
+                return Ok(());
             }
         }
+        Ok(())
     }
 
     fn get(&self) -> Option<Item<'_>> {
@@ -125,12 +480,13 @@ pub struct Include<I, S> {
 }
 
 impl<I: HtmlIterator, S: ContextualSelector> HtmlIterator for Include<I, S> {
-    fn advance(&mut self) {
-        while let Some(item) = self.inner.next() {
+    fn try_advance(&mut self) -> Result<(), Error> {
+        while let Some(item) = self.inner.try_next()? {
             if let Some(_item) = item.include(&self.selector) {
-                return;
+                return Ok(());
             }
         }
+        Ok(())
     }
 
     fn get(&self) -> Option<Item<'_>> {
@@ -138,8 +494,261 @@ impl<I: HtmlIterator, S: ContextualSelector> HtmlIterator for Include<I, S> {
     }
 }
 
+/// An iterator of the values returned for each item, made with `HtmlIterator::map`
+pub struct Map<I, F> {
+    inner: I,
+    f: F,
+}
+
+impl<I: HtmlIterator, T, F: FnMut(Item<'_>) -> T> Iterator for Map<I, F> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        self.inner.next().map(&mut self.f)
+    }
+}
+
+/// An iterator of the values returned for the items, made with `HtmlIterator::filter_map`
+pub struct FilterMap<I, F> {
+    inner: I,
+    f: F,
+}
+
+impl<I: HtmlIterator, T, F: FnMut(Item<'_>) -> Option<T>> Iterator for FilterMap<I, F> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        while let Some(item) = self.inner.next() {
+            if let Some(value) = (self.f)(item) {
+                return Some(value);
+            }
+        }
+        None
+    }
+}
+
+pub struct GroupUnder<I, S> {
+    inner: I,
+    selector: S,
+    /// The state of the current group
+    group: Option<GroupState>,
+    /// An error skipping over the rest of a dropped group, returned when the next group is read
+    error: Option<Error>,
+}
+
+#[derive(Clone, Copy)]
+struct GroupState {
+    /// The length of the path of the grouping element
+    depth: usize,
+    /// Whether the inner iterator is at the grouping element's start, which hasn't been part of the group yet
+    at_start: bool,
+    /// Whether the current item is the grouping element's end
+    at_end: bool,
+    /// Whether the group has been read to its end
+    done: bool,
+}
+
+impl<I: HtmlIterator, S: ContextualSelector> GroupUnder<I, S> {
+    /// Panics if the document can't be read, use `try_next_group` to handle errors
+    pub fn next_group(&mut self) -> Option<Group<'_, I, S>> {
+        match self.try_next_group() {
+            Ok(group) => group,
+            Err(error) => panic!("{}", error),
+        }
+    }
+
+    /// Skips to the start of the next element the selector matches
+    pub fn try_next_group(&mut self) -> Result<Option<Group<'_, I, S>>, Error> {
+        if let Some(error) = self.error.take() {
+            return Err(error);
+        }
+        self.group = None;
+        while let Some(item) = self.inner.try_next()? {
+            if matches!(item.node(), Node::Start | Node::Empty)
+                && self.selector.context_match(&item)
+            {
+                self.group = Some(GroupState {
+                    depth: item.depth(),
+                    at_start: true,
+                    at_end: false,
+                    done: false,
+                });
+                return Ok(Some(Group { groups: self }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Calls the closure with each group, like `Iterator::map`
+    pub fn map<T, F: FnMut(&mut Group<'_, I, S>) -> T>(self, f: F) -> Groups<I, S, F> {
+        Groups { groups: self, f }
+    }
+
+    /// Calls the closure with each group, keeping the values it returns, like `Iterator::filter_map`
+    pub fn filter_map<T, F: FnMut(&mut Group<'_, I, S>) -> Option<T>>(
+        self,
+        f: F,
+    ) -> FilterGroups<I, S, F> {
+        FilterGroups { groups: self, f }
+    }
+}
+
+/// The items of an element the selector of `group_under` matched
+pub struct Group<'g, I: HtmlIterator, S: ContextualSelector> {
+    groups: &'g mut GroupUnder<I, S>,
+}
+
+impl<'g, I: HtmlIterator, S: ContextualSelector> Group<'g, I, S> {
+    fn state(&mut self) -> &mut GroupState {
+        self.groups.group.as_mut().unwrap()
+    }
+}
+
+impl<'g, I: HtmlIterator, S: ContextualSelector> HtmlIterator for Group<'g, I, S> {
+    fn try_advance(&mut self) -> Result<(), Error> {
+        let state = *self.state();
+        if state.done || state.at_end {
+            self.state().done = true;
+            return Ok(());
+        }
+        let item = if state.at_start {
+            self.state().at_start = false;
+            self.groups.inner.get()
+        } else {
+            self.groups.inner.try_next()?
+        };
+        let at_end = match item {
+            Some(item) => {
+                matches!(item.node(), Node::End | Node::Empty) && item.depth() == state.depth
+            }
+            None => true,
+        };
+        self.state().at_end = at_end;
+        Ok(())
+    }
+
+    fn get(&self) -> Option<Item<'_>> {
+        let state = self.groups.group.as_ref().unwrap();
+        if state.at_start || state.done {
+            return None;
+        }
+        self.groups
+            .inner
+            .get()
+            .map(|item| item.strip_ancestors(state.depth - 1))
+    }
+}
+
+impl<'g, I: HtmlIterator, S: ContextualSelector> Drop for Group<'g, I, S> {
+    fn drop(&mut self) {
+        while !self.state().done {
+            if let Err(error) = self.try_advance() {
+                self.groups.error = Some(error);
+                return;
+            }
+        }
+    }
+}
+
+/// An iterator of the values returned for each group, made with `GroupUnder::map`
+pub struct Groups<I, S, F> {
+    groups: GroupUnder<I, S>,
+    f: F,
+}
+
+impl<I: HtmlIterator, S: ContextualSelector, T, F: FnMut(&mut Group<'_, I, S>) -> T> Iterator
+    for Groups<I, S, F>
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        let mut group = self.groups.next_group()?;
+        Some((self.f)(&mut group))
+    }
+}
+
+/// An iterator of the values returned for the groups, made with `GroupUnder::filter_map`
+pub struct FilterGroups<I, S, F> {
+    groups: GroupUnder<I, S>,
+    f: F,
+}
+
+impl<I: HtmlIterator, S: ContextualSelector, T, F: FnMut(&mut Group<'_, I, S>) -> Option<T>>
+    Iterator for FilterGroups<I, S, F>
+{
+    type Item = T;
+
+    fn next(&mut self) -> Option<T> {
+        loop {
+            let mut group = self.groups.next_group()?;
+            if let Some(value) = (self.f)(&mut group) {
+                return Some(value);
+            }
+        }
+    }
+}
+
+pub struct Unwrap<I, S> {
+    inner: I,
+    selector: S,
+    /// The path of the current item without the unwrapped elements
+    path: ElementPathBuf,
+    /// Whether each element of the inner item's path is kept
+    copied: Vec<Copied>,
+}
+
+impl<I: HtmlIterator, S: ContextualSelector> HtmlIterator for Unwrap<I, S> {
+    fn try_advance(&mut self) -> Result<(), Error> {
+        while let Some(item) = self.inner.try_next()? {
+            let selector = &self.selector;
+            self.path.copy_from(&item, &mut self.copied, |start| {
+                (!selector.context_match(&start)).then(ElementMap::new)
+            });
+            let unwrapped = match item.node() {
+                Node::Start | Node::End | Node::Empty => !self.copied.last().unwrap().kept,
+                _ => false,
+            };
+            if !unwrapped {
+                return Ok(());
+            }
+        }
+        Ok(())
+    }
+
+    fn get(&self) -> Option<Item<'_>> {
+        self.inner
+            .get()
+            .map(|item| item.with_context(self.path.as_path()))
+    }
+}
+
+pub struct MapAll<I, F> {
+    inner: I,
+    f: F,
+    /// The rewritten path of the current item
+    path: ElementPathBuf,
+    copied: Vec<Copied>,
+}
+
+impl<I: HtmlIterator, F: MapElement> HtmlIterator for MapAll<I, F> {
+    fn try_advance(&mut self) -> Result<(), Error> {
+        if let Some(item) = self.inner.try_next()? {
+            self.path.map_from(&item, &mut self.copied, &mut self.f);
+        }
+        Ok(())
+    }
+
+    fn get(&self) -> Option<Item<'_>> {
+        self.inner
+            .get()
+            .map(|item| item.with_context(self.path.as_path()))
+    }
+}
+
 #[cfg(test)]
 mod test {
+    use std::borrow::Cow;
+
     use super::*;
 
     #[test]
@@ -156,6 +765,698 @@ mod test {
         assert_eq!(&out.to_string(), test);
     }
 
+    #[test]
+    fn empty_element_identity() {
+        let test = r#"<p>hello<br/>world<img src="a.png"/></p>"#;
+        let out = HtmlIter::from_reader(test.as_bytes());
+        assert_eq!(&out.to_string(), test);
+    }
+
+    #[test]
+    fn remove_empty_elements() {
+        let test = r#"<p>hello<br class="hello"/>world<img src="a.png"/></p>"#;
+        let out = HtmlIter::from_reader(test.as_bytes()).exclude(css_select!(."hello"));
+        assert_eq!(&out.to_string(), r#"<p>helloworld<img src="a.png"/></p>"#);
+        let out = HtmlIter::from_reader(test.as_bytes()).include(css_select!("img"));
+        assert_eq!(&out.to_string(), r#"<img src="a.png"/>"#);
+    }
+
+    #[test]
+    fn other_nodes_identity() {
+        let test = r#"<?xml version="1.0" encoding="UTF-8"?><?xml-stylesheet href="a.xsl"?><feed><!-- a &amp; comment --><title><![CDATA[<b>&</b>]]></title></feed>"#;
+        let out = HtmlIter::from_reader(test.as_bytes());
+        assert_eq!(&out.to_string(), test);
+    }
+
+    #[test]
+    fn other_nodes_accessors() {
+        let test = r#"<?xml version="1.0"?><?target data?><a><!--comment--><![CDATA[cdata]]></a>"#;
+        let mut iter = HtmlIter::from_reader(test.as_bytes());
+        let mut seen = 0;
+        while let Some(item) = iter.next() {
+            if let Some(decl) = item.xml_decl() {
+                assert_eq!(decl, r#"xml version="1.0""#);
+            } else if let Some(pi) = item.processing_instruction() {
+                assert_eq!(pi, "target data");
+            } else if let Some(comment) = item.comment() {
+                assert_eq!(comment, "comment");
+                assert!(item.as_element().is_none());
+                assert!(css_select!("a").match_any(item.as_path()));
+            } else if let Some(cdata) = item.cdata() {
+                assert_eq!(cdata, "cdata");
+            } else {
+                continue;
+            }
+            seen += 1;
+        }
+        assert_eq!(seen, 4);
+    }
+
+    #[test]
+    fn mismatched_end_tag() {
+        let mut iter = HtmlIter::from_reader("<p><b>hello</p>".as_bytes());
+        let error = loop {
+            match iter.try_next() {
+                Ok(Some(_)) => {}
+                Ok(None) => panic!("expected an error"),
+                Err(error) => break error,
+            }
+        };
+        assert!(matches!(error, Error::MismatchedEndTag { .. }));
+        assert!((11..=15).contains(&error.position()));
+    }
+
+    #[test]
+    fn unexpected_eof() {
+        let mut iter =
+            HtmlIter::from_reader("<p><b>hello</b>".as_bytes()).exclude(css_select!("b"));
+        let error = loop {
+            match iter.try_next() {
+                Ok(Some(_)) => {}
+                Ok(None) => panic!("expected an error"),
+                Err(error) => break error,
+            }
+        };
+        assert!(matches!(
+            error,
+            Error::UnexpectedEof {
+                open_elements: 1,
+                position: 15
+            }
+        ));
+    }
+
+    #[test]
+    fn decoding_error() {
+        let mut iter = HtmlIter::from_reader(&b"<p>\xff</p>"[..]);
+        let error = loop {
+            match iter.try_next() {
+                Ok(Some(_)) => {}
+                Ok(None) => panic!("expected an error"),
+                Err(error) => break error,
+            }
+        };
+        assert!(matches!(error, Error::Decoding { position: 4, .. }));
+    }
+
+    struct BrokenPipe;
+
+    impl io::Write for BrokenPipe {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::ErrorKind::BrokenPipe.into())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn write_error() {
+        let out = HtmlIter::from_reader("<p>hello</p>".as_bytes()).write_into(BrokenPipe);
+        assert_eq!(out.unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+    }
+
+    #[test]
+    fn read_error_while_writing() {
+        let out = HtmlIter::from_reader("<p>hello</b>".as_bytes()).try_to_string();
+        let error = out.unwrap_err().into_inner().unwrap();
+        assert!(matches!(
+            error.downcast_ref::<Error>(),
+            Some(Error::MismatchedEndTag { .. })
+        ));
+    }
+
+    #[test]
+    fn attribute_escaping() {
+        let test = r#"<p title="a &amp; b" data-quote="&quot;q&quot;" data-lt="&lt;b&gt;">x</p>"#;
+        let mut iter = HtmlIter::from_reader(test.as_bytes()).include(css_select!("p"));
+        let item = iter.next().unwrap();
+        let element = item.as_element().unwrap();
+        assert_eq!(element.attr("title"), Some("a & b"));
+        assert_eq!(element.attr("data-quote"), Some(r#""q""#));
+        assert_eq!(element.attr("data-lt"), Some("<b>"));
+
+        let out = HtmlIter::from_reader(test.as_bytes());
+        assert_eq!(
+            &out.to_string(),
+            r#"<p title="a &amp; b" data-quote="&quot;q&quot;" data-lt="&lt;b>">x</p>"#
+        );
+        let out = HtmlIter::from_html_reader(r#"<p title='say "<hi>" &amp; bye'>"#.as_bytes());
+        assert_eq!(
+            &out.to_string(),
+            r#"<p title="say &quot;&lt;hi>&quot; &amp; bye"></p>"#
+        );
+    }
+
+    /// A badly behaved filter, which drops the start tags of some elements
+    struct DropStarts<I>(I, &'static str);
+
+    impl<I: HtmlIterator> HtmlIterator for DropStarts<I> {
+        fn try_advance(&mut self) -> Result<(), Error> {
+            while let Some(item) = self.0.try_next()? {
+                match item.as_event() {
+                    Event::Start(start) if start.name() == self.1.as_bytes() => {}
+                    _ => return Ok(()),
+                }
+            }
+            Ok(())
+        }
+
+        fn get(&self) -> Option<Item<'_>> {
+            self.0.get()
+        }
+    }
+
+    #[test]
+    fn balanced_output() {
+        let test = "<div><p><b>hello</b></p></div>";
+        let out = DropStarts(HtmlIter::from_reader(test.as_bytes()), "p");
+        assert_eq!(&out.to_string(), "<div><b>hello</b></div>");
+
+        let mut out = vec![];
+        let mut writer = HtmlWriter::from_writer(&mut out);
+        let mut iter = HtmlIter::from_reader(test.as_bytes());
+        while let Some(item) = iter.next() {
+            if !matches!(item.as_event(), Event::End(end) if end.name() == b"b") {
+                writer.write_item(item).unwrap();
+            }
+        }
+        writer.finish().unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), test);
+
+        let mut out = vec![];
+        let mut writer = HtmlWriter::from_writer(&mut out);
+        writer
+            .write_item(
+                HtmlIter::from_reader("<div>".as_bytes())
+                    .include(css_select!("div"))
+                    .next()
+                    .unwrap(),
+            )
+            .unwrap();
+        writer.finish().unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "<div></div>");
+    }
+
+    #[test]
+    fn strict_output() {
+        let test = "<div><p><b>hello</b></p></div>";
+        let out = DropStarts(HtmlIter::from_reader(test.as_bytes()), "p");
+        let mut writer = HtmlWriter::from_writer(vec![]);
+        writer.strict(true);
+        let error = out.write_with(&mut writer).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn text_accessor() {
+        let test = "<p>fish &amp; chips</p><p>peas</p>";
+        let texts: Vec<_> = {
+            let mut iter = HtmlIter::from_reader(test.as_bytes());
+            let mut texts = vec![];
+            while let Some(item) = iter.next() {
+                if let Some(text) = item.text().filter(|t| !t.is_empty()) {
+                    texts.push((matches!(text, Cow::Borrowed(_)), text.into_owned()));
+                }
+            }
+            texts
+        };
+        assert_eq!(
+            texts,
+            vec![
+                (false, "fish & chips".to_string()),
+                (true, "peas".to_string())
+            ]
+        );
+        let out = HtmlIter::from_reader(test.as_bytes());
+        assert_eq!(&out.to_string(), test);
+    }
+
+    #[test]
+    fn sibling_accessors() {
+        let test = r#"<dl><dt id="a">a</dt><dd>1</dd><dt>b<i/></dt><dd>2</dd></dl>"#;
+        let mut iter = HtmlIter::from_reader(test.as_bytes());
+        let mut siblings = vec![];
+        while let Some(item) = iter.next() {
+            if let (Node::Start, Some(element)) = (item.node(), item.as_element()) {
+                siblings.push((
+                    element.name().to_string(),
+                    element.sibling_index(),
+                    element.sibling_index_of_type(),
+                    element
+                        .previous_sibling()
+                        .map(|previous| format!("{:?}", previous)),
+                    element.has_preceding_sibling_named("dt"),
+                ));
+                if let Some(previous) = element.previous_sibling() {
+                    assert!(previous.previous_sibling().is_none());
+                    // only the first dt has no dt before it
+                    assert_eq!(
+                        previous.has_preceding_sibling_named("dt"),
+                        previous.sibling_index() > 0
+                    );
+                }
+            }
+        }
+        let dt = Some(r#"dt id="a""#.to_string());
+        assert_eq!(
+            siblings,
+            vec![
+                ("dl".to_string(), 0, 0, None, false),
+                ("dt".to_string(), 0, 0, None, false),
+                ("dd".to_string(), 1, 0, dt, true),
+                ("dt".to_string(), 2, 1, Some("dd".to_string()), true),
+                ("dd".to_string(), 3, 1, Some("dt".to_string()), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn document_modes() {
+        let test = r#"<DIV ID="main"><P Class="x">hi</p><li>a</li><LI>b</LI></div>"#;
+        let out = HtmlIter::from_reader(test.as_bytes())
+            .document_mode(DocumentMode::Html)
+            .include(css_select!((#"main") ("p"."x"), ("li":"nth-of-type"("2"))));
+        assert_eq!(&out.to_string(), r#"<P Class="x">hi</P><LI>b</LI>"#);
+
+        let out = HtmlIter::from_reader(test.as_bytes())
+            .include(css_select!("div"))
+            .try_to_string();
+        assert_eq!(out.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let test = r#"<DIV ID="main"><P>hi</P></DIV>"#;
+        let out = HtmlIter::from_reader(test.as_bytes()).include(css_select!("div", #"main"));
+        assert_eq!(&out.to_string(), "");
+
+        // attribute names are unique in the document's case rules
+        let test = r#"<a c="1" b="2" B="3"/>"#;
+        assert!(HtmlIter::from_reader(test.as_bytes())
+            .try_to_string()
+            .is_ok());
+        let out = HtmlIter::from_reader(test.as_bytes())
+            .document_mode(DocumentMode::Html)
+            .try_to_string();
+        assert_eq!(out.unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let test = r#"<a c="1" b="2" c="3"/>"#;
+        assert!(HtmlIter::from_reader(test.as_bytes())
+            .try_to_string()
+            .is_err());
+
+        let test = r#"<Div><svg viewBox="0 0 1 1"><foreignObject/></svg></Div>"#;
+        let out = HtmlIter::from_html_reader(test.as_bytes())
+            .include(css_select!(("div") > (["viewbox"])));
+        assert_eq!(
+            &out.to_string(),
+            r#"<svg viewBox="0 0 1 1"><foreignObject/></svg>"#
+        );
+    }
+
+    #[test]
+    fn namespaces() {
+        use crate::selector::NamespacedNameSelector;
+        let atom = || NamespacedNameSelector("http://www.w3.org/2005/Atom", "link");
+        let default = r#"<feed xmlns="http://www.w3.org/2005/Atom"><link href="a"/></feed>"#;
+        let prefixed = r#"<a:feed xmlns:a="http://www.w3.org/2005/Atom"><link href="b"/><a:link href="c"/></a:feed>"#;
+        let out = HtmlIter::from_reader(default.as_bytes()).include(atom());
+        assert_eq!(&out.to_string(), r#"<link href="a"/>"#);
+        let out = HtmlIter::from_reader(prefixed.as_bytes()).include(atom());
+        assert_eq!(&out.to_string(), r#"<a:link href="c"/>"#);
+
+        let mut iter = HtmlIter::from_reader(prefixed.as_bytes());
+        let mut names = vec![];
+        while let Some(item) = iter.next() {
+            if let (Node::Start | Node::Empty, Some(element)) = (item.node(), item.as_element()) {
+                names.push((
+                    element.namespace().map(str::to_string),
+                    element.local_name().to_string(),
+                ));
+            }
+        }
+        let atom_ns = Some("http://www.w3.org/2005/Atom".to_string());
+        assert_eq!(
+            names,
+            vec![
+                (atom_ns.clone(), "feed".to_string()),
+                (None, "link".to_string()),
+                (atom_ns, "link".to_string())
+            ]
+        );
+
+        let html = r#"<div>x<svg><foreignObject><Span>z</Span></foreignObject><Rect/></svg><math><mi>y</mi></math></div>"#;
+        let out = HtmlIter::from_html_reader(html.as_bytes())
+            .include(NamespacedNameSelector(SVG_NAMESPACE, "rect"));
+        assert_eq!(&out.to_string(), "<Rect/>");
+        let out = HtmlIter::from_html_reader(html.as_bytes())
+            .include(NamespacedNameSelector(XHTML_NAMESPACE, "span"));
+        assert_eq!(&out.to_string(), "<Span>z</Span>");
+        let out = HtmlIter::from_html_reader(html.as_bytes())
+            .include(NamespacedNameSelector(MATHML_NAMESPACE, "mi"));
+        assert_eq!(&out.to_string(), "<mi>y</mi>");
+        let out = HtmlIter::from_html_reader(html.as_bytes())
+            .include(NamespacedNameSelector(XHTML_NAMESPACE, "mi"));
+        assert_eq!(&out.to_string(), "");
+    }
+
+    #[test]
+    fn map_elements() {
+        use crate::map::EditAttributes;
+        let test =
+            r#"<div id="main"><p id="a" class="x">hi<br id="b"/></p><a href="/">home</a></div>"#;
+        let out = HtmlIter::from_reader(test.as_bytes()).map_all(|element| {
+            element.map_attributes(|attributes| attributes.filter(|name, _value| name != "id"))
+        });
+        assert_eq!(
+            &out.to_string(),
+            r#"<div><p class="x">hi<br/></p><a href="/">home</a></div>"#
+        );
+
+        let out = HtmlIter::from_reader(test.as_bytes()).map_all(|element| {
+            let rename = if element.is_named("p") {
+                "section".to_string()
+            } else {
+                element.name().to_string()
+            };
+            element
+                .rename(rename)
+                .map_attributes(|attributes| attributes.set("id", "z").set("rel", "nofollow"))
+        });
+        assert_eq!(
+            &out.to_string(),
+            r#"<div id="z" rel="nofollow"><section id="z" class="x" rel="nofollow">hi<br id="z" rel="nofollow"/></section><a href="/" id="z" rel="nofollow">home</a></div>"#
+        );
+
+        // set replaces the attribute in any case in html documents
+        let mixed_case = r#"<p ID="a" Class="x">hi</p>"#;
+        let set = |element: Element<'_>| {
+            element.map_attributes(|attributes| attributes.set("id", "z").set("class", "y"))
+        };
+        let out = HtmlIter::from_reader(mixed_case.as_bytes())
+            .document_mode(DocumentMode::Html)
+            .map_all(set);
+        assert_eq!(&out.to_string(), r#"<p ID="z" Class="y">hi</p>"#);
+        let out = HtmlIter::from_reader(mixed_case.as_bytes()).map_all(set);
+        assert_eq!(
+            &out.to_string(),
+            r#"<p ID="a" Class="x" id="z" class="y">hi</p>"#
+        );
+
+        // selectors after the mapping see the rewritten elements, and those before it the originals
+        let out = HtmlIter::from_reader(test.as_bytes())
+            .exclude(css_select!("a"))
+            .map_all(|element| {
+                if element.is_named("p") {
+                    element.rename("q".to_string())
+                } else {
+                    element.rename(element.name().to_string())
+                }
+            })
+            .include(css_select!(("div") > ("q")));
+        assert_eq!(
+            &out.to_string(),
+            r#"<q id="a" class="x">hi<br id="b"/></q>"#
+        );
+
+        // siblings at the same depth are each rewritten, whichever items before them were left out
+        let siblings = "<div><b>1</b><i>2</i><b>3</b><u>4</u></div>";
+        let suffix = |element: Element<'_>| {
+            let name = format!("{}-x", element.name());
+            element.rename(name)
+        };
+        let out = HtmlIter::from_reader(siblings.as_bytes())
+            .exclude(css_select!("i"))
+            .map_all(suffix);
+        assert_eq!(
+            &out.to_string(),
+            "<div-x><b-x>1</b-x><b-x>3</b-x><u-x>4</u-x></div-x>"
+        );
+
+        /// Only the text, without the start and end of the elements around it
+        struct Texts<I>(I);
+
+        impl<I: HtmlIterator> HtmlIterator for Texts<I> {
+            fn try_advance(&mut self) -> Result<(), Error> {
+                while let Some(item) = self.0.try_next()? {
+                    if item.text().map_or(false, |text| !text.is_empty()) {
+                        break;
+                    }
+                }
+                Ok(())
+            }
+
+            fn get(&self) -> Option<Item<'_>> {
+                self.0.get()
+            }
+        }
+
+        let mut iter = Texts(HtmlIter::from_reader(siblings.as_bytes())).map_all(suffix);
+        let mut paths = vec![];
+        while let Some(item) = iter.next() {
+            paths.push(format!("{:?} {}", item.as_path(), item.text().unwrap()));
+        }
+        assert_eq!(
+            paths,
+            vec![
+                "/div-x/b-x 1",
+                "/div-x/i-x 2",
+                "/div-x/b-x 3",
+                "/div-x/u-x 4"
+            ]
+        );
+        let mut iter = Texts(HtmlIter::from_reader(siblings.as_bytes())).unwrap(css_select!("i"));
+        let mut paths = vec![];
+        while let Some(item) = iter.next() {
+            paths.push(format!("{:?} {}", item.as_path(), item.text().unwrap()));
+        }
+        assert_eq!(paths, vec!["/div/b 1", "/div 2", "/div/b 3", "/div/u 4"]);
+    }
+
+    #[test]
+    fn group_elements() {
+        let test = r#"<ul><li id="a"><b>1</b><li class="nested"/></li><p>skipped</p><li id="b">2</li><li id="c"/></ul>"#;
+        let groups: Vec<_> = HtmlIter::from_reader(test.as_bytes())
+            .group_under(css_select!("li"))
+            .map(|group| {
+                let mut out = vec![];
+                while let Some(item) = group.next() {
+                    // quick-xml reads empty text between tags
+                    if item.text().as_deref() != Some("") {
+                        out.push(format!("{:?}", item));
+                    }
+                }
+                out.join(" ")
+            })
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                r#"/li id="a"/Start /li id="a"/b/Start /li id="a"/b/"1" /li id="a"/b/End /li id="a"/li class="nested"/Empty /li id="a"/End"#,
+                r#"/li id="b"/Start /li id="b"/"2" /li id="b"/End"#,
+                r#"/li id="c"/Empty"#,
+            ]
+        );
+
+        // groups which aren't read to their end skip to it
+        let ids: Vec<_> = HtmlIter::from_reader(test.as_bytes())
+            .group_under(css_select!("li"))
+            .filter_map(|group| {
+                let item = group.next()?;
+                let id = item.as_element()?.attr("id")?.to_string();
+                (id != "b").then_some(id)
+            })
+            .collect();
+        assert_eq!(ids, vec!["a", "c"]);
+
+        let mut groups = HtmlIter::from_reader(test.as_bytes()).group_under(css_select!("li"));
+        groups.next_group().unwrap().next();
+        let mut group = groups.next_group().unwrap();
+        assert_eq!(
+            group.next().unwrap().as_element().unwrap().attr("id"),
+            Some("b")
+        );
+        drop(group);
+        assert_eq!(&groups.next_group().unwrap().to_string(), r#"<li id="c"/>"#);
+        assert!(groups.next_group().is_none());
+
+        // a group can be written from inside map, as it's an iterator when borrowed
+        let groups: Vec<_> = HtmlIter::from_reader(test.as_bytes())
+            .group_under(css_select!("li"))
+            .map(|group| group.exclude(css_select!("b")).to_string())
+            .collect();
+        assert_eq!(
+            groups,
+            vec![
+                r#"<li id="a"><li class="nested"/></li>"#,
+                r#"<li id="b">2</li>"#,
+                r#"<li id="c"/>"#
+            ]
+        );
+        let all = vec![
+            r#"<li id="a"><b>1</b><li class="nested"/></li>"#,
+            r#"<li id="b">2</li>"#,
+            r#"<li id="c"/>"#,
+        ];
+        let groups: Vec<_> = HtmlIter::from_reader(test.as_bytes())
+            .group_under(css_select!("li"))
+            .map(|group| format!("{}", group.display()))
+            .collect();
+        assert_eq!(groups, all);
+        let groups: Vec<_> = HtmlIter::from_reader(test.as_bytes())
+            .group_under(css_select!("li"))
+            .map(|group| {
+                let mut out = vec![];
+                group.write_into(&mut out).unwrap();
+                String::from_utf8(out).unwrap()
+            })
+            .collect();
+        assert_eq!(groups, all);
+    }
+
+    #[test]
+    fn item_accessors() {
+        let test = r#"<html><body><a href="/">home</a></body></html>"#;
+        let mut iter = HtmlIter::from_reader(test.as_bytes());
+        let mut items = vec![];
+        while let Some(item) = iter.next() {
+            if item.text().map_or(false, |text| text.is_empty()) {
+                continue;
+            }
+            let path: Vec<_> = item.context_path().iter().map(|e| e.name()).collect();
+            items.push(format!(
+                "{} {:?} {:?} {:?} {:?} {}",
+                item.depth(),
+                item.name(),
+                item.attr("href"),
+                item.text(),
+                item.first_ancestor().map(|e| e.name()),
+                path.join("/"),
+            ));
+        }
+        assert_eq!(
+            items,
+            vec![
+                r#"1 Some("html") None None None "#,
+                r#"2 Some("body") None None Some("html") html"#,
+                r#"3 Some("a") Some("/") None Some("body") html/body"#,
+                r#"3 None None Some("home") Some("a") html/body/a"#,
+                r#"3 Some("a") None None Some("body") html/body"#,
+                r#"2 Some("body") None None Some("html") html"#,
+                r#"1 Some("html") None None None "#,
+            ]
+        );
+
+        let mut iter = HtmlIter::from_reader(test.as_bytes());
+        let path = loop {
+            let item = iter.next().unwrap();
+            if item.name() == Some("a") {
+                break item.as_path();
+            }
+        };
+        assert_eq!(path.depth(), 3);
+        assert_eq!(path.first().unwrap().name(), "html");
+        assert_eq!(
+            path.last().unwrap().attributes().collect::<Vec<_>>(),
+            vec![("href", "/")]
+        );
+    }
+
+    #[test]
+    fn display_items() {
+        use std::{fmt::Write as _, io::Write as _};
+
+        let test = r#"<?xml version="1.0"?><!DOCTYPE html><html><!--c--><p class="a&amp;b">x &lt; y<br/><![CDATA[<z>]]></p><?pi x?></html>"#;
+        let mut iter = HtmlIter::from_reader(test.as_bytes());
+        let mut out = String::new();
+        while let Some(item) = iter.next() {
+            write!(&mut out, "{}", item).unwrap();
+        }
+        assert_eq!(out, test);
+
+        let out = format!(
+            "{}",
+            HtmlIter::from_reader(test.as_bytes())
+                .include(css_select!("p"))
+                .display()
+        );
+        assert_eq!(
+            out,
+            r#"<p class="a&amp;b">x &lt; y<br/><![CDATA[<z>]]></p>"#
+        );
+
+        // the iterator is only written once, and the output is balanced like `write_into`
+        let display = HtmlIter::from_reader("<a><b>x</b></a>".as_bytes())
+            .exclude(css_select!("b"))
+            .display();
+        let mut out = vec![];
+        write!(&mut out, "{}", display).unwrap();
+        write!(&mut out, "{}", display).unwrap();
+        assert_eq!(out, b"<a></a>");
+
+        let display = HtmlIter::from_reader("<a></b>".as_bytes()).display();
+        assert!(write!(&mut String::new(), "{}", display).is_err());
+    }
+
+    #[test]
+    fn unwrap_elements() {
+        let test =
+            r#"<div><font color="red"><span>hi</span> <b>there</b><span/></font><p>x</p></div>"#;
+        let out = HtmlIter::from_reader(test.as_bytes()).unwrap(css_select!("font", "span"));
+        assert_eq!(&out.to_string(), "<div>hi <b>there</b><p>x</p></div>");
+
+        // selectors after it don't see the unwrapped elements in the paths
+        let out = HtmlIter::from_reader(test.as_bytes())
+            .unwrap(css_select!("font"))
+            .include(css_select!(("div") > ("b")));
+        assert_eq!(&out.to_string(), "<b>there</b>");
+        let out = HtmlIter::from_reader(test.as_bytes())
+            .unwrap(css_select!("font"))
+            .include(css_select!(("div") > ("span":"first-child")));
+        assert_eq!(&out.to_string(), "<span>hi</span>");
+
+        let mut iter = HtmlIter::from_reader(test.as_bytes()).unwrap(css_select!("font"));
+        let mut paths = vec![];
+        while let Some(item) = iter.next() {
+            if let Some(text) = item.text().filter(|text| !text.is_empty()) {
+                paths.push(format!("{:?} {}", item.as_path(), text));
+            }
+        }
+        assert_eq!(
+            paths,
+            vec!["/div/span hi", "/div  ", "/div/b there", "/div/p x"]
+        );
+    }
+
+    #[test]
+    fn root_element() {
+        let test = r#"<div><ul><li>a</li></ul></div>"#;
+        // include and group_under leave the elements where they are in the document
+        let out = HtmlIter::from_reader(test.as_bytes())
+            .include(css_select!("ul"))
+            .include(css_select!(:"root"));
+        assert_eq!(&out.to_string(), "");
+        let out = HtmlIter::from_reader(r#"<ul><li>a</li></ul>"#.as_bytes())
+            .include(css_select!("ul"))
+            .include(css_select!(:"root"));
+        assert_eq!(&out.to_string(), "<ul><li>a</li></ul>");
+        let groups = HtmlIter::from_reader(test.as_bytes())
+            .group_under(css_select!("ul"))
+            .filter_map(|group| {
+                group
+                    .next()
+                    .map(|item| css_select!(:"root").context_match(&item))
+            });
+        assert_eq!(groups.collect::<Vec<_>>(), [false]);
+
+        // the elements unwrap removes are left out of the document after it
+        let out = HtmlIter::from_reader(test.as_bytes())
+            .unwrap(css_select!("div"))
+            .include(css_select!(:"root"));
+        assert_eq!(&out.to_string(), "<ul><li>a</li></ul>");
+        let out = HtmlIter::from_reader(test.as_bytes())
+            .map_all(|element| element.rename(element.name().replace("div", "section")))
+            .include(css_select!(:"root"));
+        assert_eq!(&out.to_string(), "<section><ul><li>a</li></ul></section>");
+    }
+
     #[test]
     fn remove_elements() {
         let test = r#"<!DOCTYPE html><html><head></head><body><p class="hello"><b>hello</b></p><p>world!</p></body></html>"#;
@@ -172,4 +1473,43 @@ mod test {
         let out = HtmlIter::from_reader(test.as_bytes()).include(css_select!((#"main") ("p")));
         assert_eq!(&out.to_string(), "<p><b>hello</b></p><p>world!</p>");
     }
+
+    #[cfg(feature = "streaming-iterator")]
+    #[test]
+    fn streaming_iterator_combinators() {
+        use streaming_iterator::StreamingIterator;
+
+        let test = r#"<div id="main"><p>hello</p><p>world!</p></div><p>side</p>"#;
+        let iter = HtmlIter::from_reader(test.as_bytes()).include(css_select!((#"main") ("p")));
+        let mut texts = iter.streaming().filter(|item| item.text().is_some());
+        let mut out = vec![];
+        while let Some(item) = texts.next() {
+            out.push(item.text().unwrap().into_owned());
+        }
+        assert_eq!(out, ["hello", "world!"]);
+
+        let iter = HtmlIter::from_reader(test.as_bytes()).exclude(css_select!((#"main")));
+        let elements = iter.streaming().filter(|item| item.as_element().is_some());
+        // the start and end of the side paragraph
+        assert_eq!(elements.count(), 2);
+
+        // errors end the stream, and are kept rather than panicking
+        let mut stream = HtmlIter::from_reader("<p>a</b>".as_bytes()).streaming();
+        let mut names = vec![];
+        while let Some(item) = stream.next() {
+            names.extend(item.name().map(String::from));
+        }
+        assert_eq!(names, ["p"]);
+        assert!(stream.next().is_none());
+        assert!(stream.error().is_some());
+
+        // the plain adaptors keep their own methods with the trait in scope
+        let mut iter = HtmlIter::from_reader(test.as_bytes()).include(css_select!("p"));
+        assert!(iter.next().unwrap().as_element().is_some());
+        let texts: Vec<_> = iter
+            .filter_map(|item| item.text().map(|text| text.into_owned()))
+            .filter(|text| !text.is_empty())
+            .collect();
+        assert_eq!(texts, ["hello", "world!", "side"]);
+    }
 }
@@ -0,0 +1,140 @@
+//! Rewriting elements while streaming, with `HtmlIterator::map_all`
+//!
+//! A rewrite is a description of the changes to make to an element rather than a copy of it, so it doesn't borrow from the document and the rewritten element is written straight into the adaptor's buffers.
+
+use crate::iteritem::{names_eq, Attributes, Element};
+
+/// A closure which rewrites elements, for `HtmlIterator::map_all`
+pub trait MapElement {
+    type Name: AsRef<str>;
+    type Attributes: EditAttributes;
+
+    fn map_element(&mut self, element: Element<'_>) -> ElementMap<Self::Name, Self::Attributes>;
+}
+
+impl<F, N, E> MapElement for F
+where
+    F: FnMut(Element<'_>) -> ElementMap<N, E>,
+    N: AsRef<str>,
+    E: EditAttributes,
+{
+    type Name = N;
+    type Attributes = E;
+
+    fn map_element(&mut self, element: Element<'_>) -> ElementMap<N, E> {
+        self(element)
+    }
+}
+
+/// The changes to make to an element, made with `Element::map_attributes` or `Element::rename`
+pub struct ElementMap<N, E> {
+    pub(crate) name: Option<N>,
+    pub(crate) attributes: E,
+}
+
+impl ElementMap<&'static str, Unchanged> {
+    pub(crate) fn new() -> Self {
+        ElementMap {
+            name: None,
+            attributes: Unchanged,
+        }
+    }
+}
+
+impl<N: AsRef<str>, E: EditAttributes> ElementMap<N, E> {
+    /// Changes the element's name, its end tag is renamed too
+    pub fn rename<M: AsRef<str>>(self, name: M) -> ElementMap<M, E> {
+        ElementMap {
+            name: Some(name),
+            attributes: self.attributes,
+        }
+    }
+
+    /// Changes the element's attributes, eg. `element.map_attributes(|attributes| attributes.filter(|name, _value| name != "id"))`
+    pub fn map_attributes<F: EditAttributes>(self, f: impl FnOnce(E) -> F) -> ElementMap<N, F> {
+        ElementMap {
+            name: self.name,
+            attributes: f(self.attributes),
+        }
+    }
+}
+
+/// Changes to the attributes of an element, applied in the order they were chained
+pub trait EditAttributes {
+    /// Calls `f` with the name and value of each attribute of the rewritten element
+    fn edit(&self, attributes: Attributes<'_>, f: &mut dyn FnMut(&str, &str));
+
+    /// Keeps the attributes for which the predicate is true
+    fn filter<P: Fn(&str, &str) -> bool>(self, predicate: P) -> Filter<Self, P>
+    where
+        Self: Sized,
+    {
+        Filter {
+            inner: self,
+            predicate,
+        }
+    }
+
+    /// Replaces the value of the attribute, or adds it after the others if the element doesn't have it, names are compared as the document compares them
+    fn set<S: AsRef<str>>(self, name: S, value: S) -> Set<Self, S>
+    where
+        Self: Sized,
+    {
+        Set {
+            inner: self,
+            name,
+            value,
+        }
+    }
+}
+
+/// The attributes as they are in the document
+pub struct Unchanged;
+
+impl EditAttributes for Unchanged {
+    fn edit(&self, attributes: Attributes<'_>, f: &mut dyn FnMut(&str, &str)) {
+        for (name, value) in attributes {
+            f(name, value);
+        }
+    }
+}
+
+pub struct Filter<E, P> {
+    inner: E,
+    predicate: P,
+}
+
+impl<E: EditAttributes, P: Fn(&str, &str) -> bool> EditAttributes for Filter<E, P> {
+    fn edit(&self, attributes: Attributes<'_>, f: &mut dyn FnMut(&str, &str)) {
+        self.inner.edit(attributes, &mut |name, value| {
+            if (self.predicate)(name, value) {
+                f(name, value);
+            }
+        });
+    }
+}
+
+pub struct Set<E, S> {
+    inner: E,
+    name: S,
+    value: S,
+}
+
+impl<E: EditAttributes, S: AsRef<str>> EditAttributes for Set<E, S> {
+    fn edit(&self, attributes: Attributes<'_>, f: &mut dyn FnMut(&str, &str)) {
+        let (set_name, set_value) = (self.name.as_ref(), self.value.as_ref());
+        let mut replaced = false;
+        let html = attributes.html();
+        self.inner.edit(attributes, &mut |name, value| {
+            if !names_eq(html, name, set_name) {
+                f(name, value);
+            } else if !replaced {
+                f(name, set_value);
+                replaced = true;
+            }
+        });
+        if !replaced {
+            f(set_name, set_value);
+        }
+    }
+}
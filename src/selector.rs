@@ -1,6 +1,14 @@
 use crate::iteritem::{Element, ElementPath, Item};
 
-/// Selects elements using a syntax similar to css 1 selectors, supporting css 1 selectors except pseudo-elements and pseudo classes
+mod parser;
+
+pub use parser::{parse, ParseError};
+
+/// Selects elements using a syntax similar to css 1 selectors, supporting css 1 selectors except pseudo-elements and pseudo classes, css 2 attribute selectors and child combinators, and css 3 sibling combinators, positional pseudo classes, `:not()`, `:is()` and `:root`
+///
+/// Selectors separated by commas select elements which any of them would select.
+///
+/// The selector before `~` can only be an element name, and the selector before `+` can't have siblings itself, as only the names of earlier siblings and the previous sibling are kept while streaming.
 ///
 /// ```
 /// use xmliter::css_select;
@@ -9,39 +17,172 @@ use crate::iteritem::{Element, ElementPath, Item};
 /// css_select!("p"."quote");
 /// css_select!(."quote");
 /// css_select!(#"main");
+/// css_select!(["lang"|="en"]);
+/// css_select!("a"["href"^="https://" i]."external");
 /// css_select!((#"main") ("p"."quote"));
+/// css_select!(("ul") > ("li"));
+/// css_select!(("li":"first-child"));
+/// css_select!(("tr":"nth-of-type"("2n+1")));
+/// css_select!(("dt") + ("dd"));
+/// css_select!(("h1") ~ (."note"));
+/// css_select!("p":"not"(."ad", ."promo"));
+/// css_select!((:"root") > (:"is"("h1", "h2"."title")));
+/// css_select!("h1", "h2", "h3");
+/// css_select!((#"main") ("p"), ("aside"));
 /// ```
 #[macro_export]
 macro_rules! css_select {
     (@inner [($($head:tt)+)] -> [$selector:expr]) => {
         ($crate::selector::ContextualSelectCons($selector , css_select!($($head)+)))
     };
+    (@inner [($($previous:tt)+) + ($($head:tt)+) $($tail:tt)*] -> [$selector:expr]) => {
+        css_select!(@inner [(@adjacent ($($previous)+) ($($head)+)) $($tail)*] -> [$selector])
+    };
+    (@inner [($name:literal) ~ ($($head:tt)+) $($tail:tt)*] -> [$selector:expr]) => {
+        css_select!(@inner [(@general $name ($($head)+)) $($tail)*] -> [$selector])
+    };
+    (@inner [($($head:tt)+) > $($tail:tt)*] -> [$selector:expr]) => {
+        css_select!(@inner [$($tail)*] -> [$crate::selector::ParentSelectCons($selector , css_select!($($head)+))])
+    };
     (@inner [($($head:tt)+) $($tail:tt)*] -> [$selector:expr]) => {
         css_select!(@inner [$($tail)*] -> [$crate::selector::ContextSelectCons($selector , css_select!($($head)+))])
     };
-    ($(($($selectors:tt)+))+) => {
-        css_select!(@inner [$(($($selectors)+))+] -> [$crate::selector::MatchAll])
+    (@adjacent (@$kind:ident $($previous:tt)*) $head:tt) => {
+        compile_error!("only the previous sibling is kept, so its own siblings can't be matched")
+    };
+    (@adjacent ($($previous:tt)+) ($($head:tt)+)) => {
+        $crate::selector::AdjacentSiblingSelector(css_select!($($previous)+), css_select!($($head)+))
+    };
+    (@general $name:literal ($($head:tt)+)) => {
+        $crate::selector::GeneralSiblingSelector(Some($name), css_select!($($head)+))
+    };
+    (@compound [$selector:expr]) => {
+        $selector
+    };
+    (@compound [$selector:expr] : "first-child" $($tail:tt)*) => {
+        css_select!(@compound [$crate::selector::Selector::and(
+            $selector,
+            $crate::selector::NthChildSelector($crate::selector::Nth::FIRST),
+        )] $($tail)*)
+    };
+    (@compound [$selector:expr] : "nth-child" ($nth:literal) $($tail:tt)*) => {
+        css_select!(@compound [$crate::selector::Selector::and(
+            $selector,
+            $crate::selector::NthChildSelector(css_select!(@nth $nth)),
+        )] $($tail)*)
+    };
+    (@compound [$selector:expr] : "nth-of-type" ($nth:literal) $($tail:tt)*) => {
+        css_select!(@compound [$crate::selector::Selector::and(
+            $selector,
+            $crate::selector::NthOfTypeSelector(css_select!(@nth $nth)),
+        )] $($tail)*)
+    };
+    (@compound [$selector:expr] : "root" $($tail:tt)*) => {
+        css_select!(@compound [$crate::selector::Selector::and(
+            $selector,
+            $crate::selector::RootSelector,
+        )] $($tail)*)
+    };
+    (@compound [$selector:expr] : "not" ($($not:tt)+) $($tail:tt)*) => {
+        css_select!(@compound [$crate::selector::Selector::and(
+            $selector,
+            $crate::selector::Selector::not(css_select!(@list [$crate::selector::Selector::or_element] [] [] $($not)+)),
+        )] $($tail)*)
     };
-    ($name:literal.$class:literal) => {
-        $crate::selector::Selector::and(
-            $crate::selector::NameSelector($name),
+    (@compound [$selector:expr] : "is" ($($any:tt)+) $($tail:tt)*) => {
+        css_select!(@compound [$crate::selector::Selector::and(
+            $selector,
+            css_select!(@list [$crate::selector::Selector::or_element] [] [] $($any)+),
+        )] $($tail)*)
+    };
+    // splits a comma separated list of selectors, combining each with the ones before it using the function
+    (@list [$($combine:tt)+] [$($list:tt)*] [$($current:tt)+] , $($tail:tt)+) => {
+        css_select!(@list [$($combine)+] [css_select!(@combine [$($combine)+] [$($list)*] $($current)+)] [] $($tail)+)
+    };
+    (@list [$($combine:tt)+] [$($list:tt)*] [$($current:tt)*] $next:tt $($tail:tt)*) => {
+        css_select!(@list [$($combine)+] [$($list)*] [$($current)* $next] $($tail)*)
+    };
+    (@list [$($combine:tt)+] [$($list:tt)*] [$($current:tt)+]) => {
+        css_select!(@combine [$($combine)+] [$($list)*] $($current)+)
+    };
+    (@combine [$($combine:tt)+] [] $($current:tt)+) => {
+        css_select!(@selector $($current)+)
+    };
+    (@combine [$($combine:tt)+] [$($list:tt)+] $($current:tt)+) => {
+        $($combine)+($($list)+, css_select!(@selector $($current)+))
+    };
+    (@nth $nth:literal) => {{
+        const NTH: $crate::selector::Nth = $crate::selector::Nth::from_literal($nth);
+        NTH
+    }};
+    (@compound [$selector:expr] .$class:literal $($tail:tt)*) => {
+        css_select!(@compound [$crate::selector::Selector::and(
+            $selector,
             $crate::selector::ClassSelector($class),
-        )
+        )] $($tail)*)
     };
-    ($name:literal#$id:literal) => {
-        $crate::selector::Selector::and(
-            $crate::selector::NameSelector($name),
+    (@compound [$selector:expr] #$id:literal $($tail:tt)*) => {
+        css_select!(@compound [$crate::selector::Selector::and(
+            $selector,
             $crate::selector::IdSelector($id),
-        )
+        )] $($tail)*)
+    };
+    (@compound [$selector:expr] [$($attribute:tt)+] $($tail:tt)*) => {
+        css_select!(@compound [$crate::selector::Selector::and(
+            $selector,
+            css_select!(@attribute $($attribute)+),
+        )] $($tail)*)
+    };
+    (@attribute $name:literal) => {
+        $crate::selector::AttributeSelector {
+            name: $name,
+            value: None,
+            case_insensitive: false,
+        }
+    };
+    // `=`, `|=`, `^=` and `*=` are single tokens
+    (@attribute $name:literal $operator:tt $value:literal $($flag:ident)?) => {
+        css_select!(@attribute $name, stringify!($operator), $value, $($flag)?)
+    };
+    // `~=` and `$=` are two tokens
+    (@attribute $name:literal $operator:tt = $value:literal $($flag:ident)?) => {
+        css_select!(@attribute $name, stringify!($operator), $value, $($flag)?)
+    };
+    (@attribute $name:expr, $operator:expr, $value:literal, $($flag:ident)?) => {
+        $crate::selector::AttributeSelector {
+            name: $name,
+            value: Some(({
+                const OPERATOR: $crate::selector::AttributeOperator =
+                    $crate::selector::AttributeOperator::from_token($operator);
+                OPERATOR
+            }, $value)),
+            case_insensitive: {
+                const CASE_INSENSITIVE: bool =
+                    $crate::selector::AttributeSelector::<&str>::flag(stringify!($($flag)?));
+                CASE_INSENSITIVE
+            },
+        }
+    };
+    (@selector ($($head:tt)+) $($tail:tt)*) => {
+        css_select!(@inner [($($head)+) $($tail)*] -> [$crate::selector::MatchAll])
     };
-    ($name:literal) => {
-        $crate::selector::NameSelector($name)
+    (@selector $name:literal $($tail:tt)*) => {
+        css_select!(@compound [$crate::selector::NameSelector($name)] $($tail)*)
     };
-    (.$class:literal) => {
-        $crate::selector::ClassSelector($class)
+    (@selector .$class:literal $($tail:tt)*) => {
+        css_select!(@compound [$crate::selector::ClassSelector($class)] $($tail)*)
     };
-    (#$id:literal) => {
-        $crate::selector::IdSelector($id)
+    (@selector #$id:literal $($tail:tt)*) => {
+        css_select!(@compound [$crate::selector::IdSelector($id)] $($tail)*)
+    };
+    (@selector [$($attribute:tt)+] $($tail:tt)*) => {
+        css_select!(@compound [css_select!(@attribute $($attribute)+)] $($tail)*)
+    };
+    (@selector : $($tail:tt)+) => {
+        css_select!(@compound [$crate::selector::MatchAll] : $($tail)+)
+    };
+    ($($selector:tt)+) => {
+        css_select!(@list [$crate::selector::ContextualSelector::or] [] [] $($selector)+)
     };
 }
 
@@ -54,6 +195,20 @@ pub trait Selector {
     {
         AndSelector(self, other)
     }
+
+    fn or_element<O: Selector>(self, other: O) -> AnySelector<Self, O>
+    where
+        Self: Sized,
+    {
+        AnySelector(self, other)
+    }
+
+    fn not(self) -> NotSelector<Self>
+    where
+        Self: Sized,
+    {
+        NotSelector(self)
+    }
 }
 
 pub trait ContextualSelector {
@@ -91,32 +246,393 @@ where
     }
 }
 
-pub struct NameSelector(pub &'static str);
+impl Selector for Box<dyn Selector> {
+    fn is_match(&self, element: &Element<'_>) -> bool {
+        (**self).is_match(element)
+    }
+}
+
+impl ContextualSelector for Box<dyn ContextualSelector> {
+    fn context_match(&self, item: &Item<'_>) -> bool {
+        (**self).context_match(item)
+    }
+
+    fn match_any(&self, context: ElementPath<'_>) -> bool {
+        (**self).match_any(context)
+    }
+}
+
+impl OnlyContextualSelector for Box<dyn OnlyContextualSelector> {
+    fn match_any(&self, context: ElementPath) -> bool {
+        (**self).match_any(context)
+    }
+}
+
+/// Matches the element name, ignoring ASCII case in html documents, `css_select!` uses `&'static str` and `parse` uses owned `String`s
+pub struct NameSelector<S = &'static str>(pub S);
+
+impl<S: AsRef<str>> Selector for NameSelector<S> {
+    fn is_match(&self, element: &Element<'_>) -> bool {
+        element.is_named(self.0.as_ref())
+    }
+}
+
+/// Matches the namespace URI and the local name, whichever prefix the document binds the namespace to, eg. `NamespacedNameSelector("http://www.w3.org/2005/Atom", "link")`
+pub struct NamespacedNameSelector<S = &'static str>(pub S, pub S);
+
+impl<S: AsRef<str>> Selector for NamespacedNameSelector<S> {
+    fn is_match(&self, element: &Element<'_>) -> bool {
+        element.is_named_in(self.0.as_ref(), self.1.as_ref())
+    }
+}
+
+pub struct ClassSelector<S = &'static str>(pub S);
+
+impl<S: AsRef<str>> Selector for ClassSelector<S> {
+    fn is_match(&self, element: &Element<'_>) -> bool {
+        element.classes().any(|class| class == self.0.as_ref())
+    }
+}
+
+pub struct IdSelector<S = &'static str>(pub S);
+
+impl<S: AsRef<str>> Selector for IdSelector<S> {
+    fn is_match(&self, element: &Element<'_>) -> bool {
+        element.attr("id") == Some(self.0.as_ref())
+    }
+}
+
+/// How an `AttributeSelector` compares the attribute value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttributeOperator {
+    /// `=`, the value is exactly the same
+    Exact,
+    /// `~=`, the value is a whitespace separated list containing the same value
+    Includes,
+    /// `|=`, the value is exactly the same or starts with it followed by `-`
+    DashMatch,
+    /// `^=`, the value starts with it
+    Prefix,
+    /// `$=`, the value ends with it
+    Suffix,
+    /// `*=`, the value contains it
+    Substring,
+}
+
+impl AttributeOperator {
+    /// Used by `css_select!`, the tokens up to the `=` of the operator
+    #[doc(hidden)]
+    pub const fn from_token(token: &str) -> Self {
+        match token.as_bytes() {
+            b"=" => AttributeOperator::Exact,
+            b"~" => AttributeOperator::Includes,
+            b"|=" => AttributeOperator::DashMatch,
+            b"^=" => AttributeOperator::Prefix,
+            b"$" => AttributeOperator::Suffix,
+            b"*=" => AttributeOperator::Substring,
+            _ => panic!("unknown attribute selector operator"),
+        }
+    }
+
+    fn is_match(self, value: &str, expected: &str, case_insensitive: bool) -> bool {
+        let eq = |a: &[u8], b: &[u8]| {
+            if case_insensitive {
+                a.eq_ignore_ascii_case(b)
+            } else {
+                a == b
+            }
+        };
+        let (value, expected) = (value.as_bytes(), expected.as_bytes());
+        let starts_with =
+            || value.len() >= expected.len() && eq(&value[..expected.len()], expected);
+        match self {
+            AttributeOperator::Exact => eq(value, expected),
+            AttributeOperator::Includes => {
+                !expected.is_empty()
+                    && value
+                        .split(u8::is_ascii_whitespace)
+                        .any(|word| eq(word, expected))
+            }
+            AttributeOperator::DashMatch => {
+                starts_with() && matches!(value.get(expected.len()), None | Some(b'-'))
+            }
+            AttributeOperator::Prefix => !expected.is_empty() && starts_with(),
+            AttributeOperator::Suffix => {
+                !expected.is_empty()
+                    && value.len() >= expected.len()
+                    && eq(&value[value.len() - expected.len()..], expected)
+            }
+            AttributeOperator::Substring => {
+                !expected.is_empty()
+                    && value
+                        .windows(expected.len())
+                        .any(|window| eq(window, expected))
+            }
+        }
+    }
+}
+
+/// Matches elements with an attribute, and optionally compares its value
+pub struct AttributeSelector<S = &'static str> {
+    pub name: S,
+    pub value: Option<(AttributeOperator, S)>,
+    /// Compares the value ASCII case-insensitively, the `i` flag
+    pub case_insensitive: bool,
+}
+
+impl<S> AttributeSelector<S> {
+    /// Used by `css_select!`, whether the flag is `i` rather than `s` or none
+    #[doc(hidden)]
+    pub const fn flag(flag: &str) -> bool {
+        match flag.as_bytes() {
+            b"" | b"s" => false,
+            b"i" => true,
+            _ => panic!("unknown attribute selector flag"),
+        }
+    }
+}
+
+impl<S: AsRef<str>> Selector for AttributeSelector<S> {
+    fn is_match(&self, element: &Element<'_>) -> bool {
+        match (element.attr(self.name.as_ref()), &self.value) {
+            (None, _) => false,
+            (Some(_), None) => true,
+            (Some(value), Some((operator, expected))) => {
+                operator.is_match(value, expected.as_ref(), self.case_insensitive)
+            }
+        }
+    }
+}
+
+/// The `an+b` argument of `:nth-child` and `:nth-of-type`, which matches the positions counting from 1 for any n of 0 or more
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Nth {
+    pub a: i64,
+    pub b: i64,
+}
+
+impl Nth {
+    /// Matches only the first position, for `:first-child`
+    pub const FIRST: Nth = Nth { a: 0, b: 1 };
+
+    /// Parses `odd`, `even`, `b`, `an` or `an+b` where a and b can be signed and a can be left out
+    pub const fn parse(nth: &str) -> Option<Nth> {
+        let bytes = nth.as_bytes();
+        let (mut i, mut end) = (0, bytes.len());
+        while i < end && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        while end > i && bytes[end - 1].is_ascii_whitespace() {
+            end -= 1;
+        }
+        if is_keyword(bytes, i, end, b"odd") {
+            return Some(Nth { a: 2, b: 1 });
+        }
+        if is_keyword(bytes, i, end, b"even") {
+            return Some(Nth { a: 2, b: 0 });
+        }
+        let mut sign = 1;
+        if i < end && (bytes[i] == b'+' || bytes[i] == b'-') {
+            if bytes[i] == b'-' {
+                sign = -1;
+            }
+            i += 1;
+        }
+        let digits = i;
+        let value = match parse_digits(bytes, i, end) {
+            Some((value, digits_end)) => {
+                i = digits_end;
+                value
+            }
+            None => return None,
+        };
+        if i == end || !bytes[i].eq_ignore_ascii_case(&b'n') {
+            // only b
+            return if i == end && i > digits {
+                Some(Nth {
+                    a: 0,
+                    b: sign * value,
+                })
+            } else {
+                None
+            };
+        }
+        let a = if i == digits { sign } else { sign * value };
+        i += 1;
+        while i < end && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        if i == end {
+            return Some(Nth { a, b: 0 });
+        }
+        let sign = match bytes[i] {
+            b'+' => 1,
+            b'-' => -1,
+            _ => return None,
+        };
+        i += 1;
+        while i < end && bytes[i].is_ascii_whitespace() {
+            i += 1;
+        }
+        let digits = i;
+        let value = match parse_digits(bytes, i, end) {
+            Some((value, digits_end)) => {
+                i = digits_end;
+                value
+            }
+            None => return None,
+        };
+        if i == end && i > digits {
+            Some(Nth { a, b: sign * value })
+        } else {
+            None
+        }
+    }
+
+    /// Used by `css_select!`
+    #[doc(hidden)]
+    pub const fn from_literal(nth: &str) -> Nth {
+        match Nth::parse(nth) {
+            Some(nth) => nth,
+            None => panic!("expected an+b, odd or even"),
+        }
+    }
+
+    /// Whether the position, counting from 1, is `an+b` for any n of 0 or more
+    pub fn is_match(self, position: usize) -> bool {
+        // wide enough that no a, b or position can overflow
+        let (a, offset) = (self.a as i128, position as i128 - self.b as i128);
+        if a == 0 {
+            offset == 0
+        } else {
+            offset % a == 0 && offset / a >= 0
+        }
+    }
+}
+
+/// Reads the digits from the position as a number along with the position after them, `None` if it doesn't fit in an i64 even when it is negated
+const fn parse_digits(bytes: &[u8], mut i: usize, end: usize) -> Option<(i64, usize)> {
+    let mut value: i64 = 0;
+    while i < end && bytes[i].is_ascii_digit() {
+        value = match value.checked_mul(10) {
+            Some(value) => match value.checked_add((bytes[i] - b'0') as i64) {
+                Some(value) => value,
+                None => return None,
+            },
+            None => return None,
+        };
+        i += 1;
+    }
+    Some((value, i))
+}
 
-impl Selector for NameSelector {
+/// Whether the bytes between the positions are the keyword, ASCII case-insensitively
+const fn is_keyword(bytes: &[u8], start: usize, end: usize, keyword: &[u8]) -> bool {
+    if end - start != keyword.len() {
+        return false;
+    }
+    let mut i = 0;
+    while i < keyword.len() {
+        if !bytes[start + i].eq_ignore_ascii_case(&keyword[i]) {
+            return false;
+        }
+        i += 1;
+    }
+    true
+}
+
+/// Matches elements by their position among their siblings, `:nth-child(an+b)` or `:first-child`
+pub struct NthChildSelector(pub Nth);
+
+impl Selector for NthChildSelector {
     fn is_match(&self, element: &Element<'_>) -> bool {
-        *self.0 == *element.name()
+        self.0.is_match(element.sibling_index() + 1)
     }
 }
 
-pub struct ClassSelector(pub &'static str);
+/// Matches elements by their position among their siblings with the same name, `:nth-of-type(an+b)`
+pub struct NthOfTypeSelector(pub Nth);
 
-impl Selector for ClassSelector {
+impl Selector for NthOfTypeSelector {
     fn is_match(&self, element: &Element<'_>) -> bool {
-        element.classes().any(|class| class == self.0)
+        self.0.is_match(element.sibling_index_of_type() + 1)
     }
 }
 
-pub struct IdSelector(pub &'static str);
+/// Matches elements which the second selector matches and whose previous sibling the first selector matches, `A + B`
+///
+/// Only the previous sibling of elements in the path is kept, so the first selector can't match siblings itself.
+pub struct AdjacentSiblingSelector<A, B>(pub A, pub B);
+
+impl<A: Selector, B: Selector> Selector for AdjacentSiblingSelector<A, B> {
+    fn is_match(&self, element: &Element<'_>) -> bool {
+        self.1.is_match(element)
+            && element
+                .previous_sibling()
+                .map_or(false, |previous| self.0.is_match(&previous))
+    }
+}
+
+/// Matches elements which the selector matches and which have any earlier sibling with the name, `name ~ B`, or any earlier sibling at all for `None`, `* ~ B`
+///
+/// Only the names of earlier siblings are kept, so they can't be matched with other selectors.
+pub struct GeneralSiblingSelector<S, B>(pub Option<S>, pub B);
 
-impl Selector for IdSelector {
+impl<S: AsRef<str>, B: Selector> Selector for GeneralSiblingSelector<S, B> {
     fn is_match(&self, element: &Element<'_>) -> bool {
-        element
-            .attributes()
-            .any(|attr| attr.name == "id" && attr.value == self.0)
+        self.1.is_match(element)
+            && match &self.0 {
+                Some(name) => element.has_preceding_sibling_named(name.as_ref()),
+                None => element.sibling_index() > 0,
+            }
     }
 }
 
+/// How an element relates to the element matched by the preceding selector
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Combinator {
+    /// Any ancestor, ` ` in css
+    Descendant,
+    /// Only the parent, `>` in css
+    Child,
+}
+
+/// A contextual selector, each selector is paired with how its element relates to the element matched by the preceding selector, the combinator of the first selector is ignored
+impl<S: Selector> ContextualSelector for [(Combinator, S)] {
+    fn context_match(&self, item: &Item<'_>) -> bool {
+        match self.split_last() {
+            Some(((combinator, end_matcher), rest)) => {
+                item.as_element()
+                    .map_or(false, |element| end_matcher.is_match(&element))
+                    && match_combined(rest, *combinator, item.context_path())
+            }
+            None => true,
+        }
+    }
+}
+
+/// Matches the last selector with an element in the context allowed by the combinator, backtracking to more distant ancestors if the rest don't match
+fn match_combined<S: Selector>(
+    selectors: &[(Combinator, S)],
+    combinator: Combinator,
+    mut context: ElementPath<'_>,
+) -> bool {
+    let ((next_combinator, matcher), rest) = match selectors.split_last() {
+        Some(split) => split,
+        None => return true,
+    };
+    while let Some((element, ancestors)) = context.split_last() {
+        if matcher.is_match(&element) && match_combined(rest, *next_combinator, ancestors) {
+            return true;
+        }
+        if combinator == Combinator::Child {
+            return false;
+        }
+        context = ancestors;
+    }
+    false
+}
+
 /// A contextual selector, the last selector must match the element exactly and the preceding must match elements in the context in that order
 impl<S: Selector> ContextualSelector for [S] {
     fn context_match(&self, item: &Item<'_>) -> bool {
@@ -160,14 +676,17 @@ impl OnlyContextualSelector for MatchAll {
 }
 
 /// Matches something in the context, then continues by using the second matcher for the remaining context
+///
+/// Each ancestor the selector matches is tried in turn, from the innermost, until the rest of the context matches the rest of the selector. For selectors made only of descendant combinators the innermost match is always enough, so this only changes what matches when there is a `>` further up.
 pub struct ContextSelectCons<C, A>(pub C, pub A);
 
 impl<C: OnlyContextualSelector, A: Selector> OnlyContextualSelector for ContextSelectCons<C, A> {
     fn match_any(&self, mut context: ElementPath<'_>) -> bool {
         while let Some((last, rest)) = context.split_last() {
             let element = last;
-            if self.1.is_match(&element) {
-                return self.0.match_any(rest);
+            // keep looking further up if the rest doesn't match, a parent selector in the rest may only match from a more distant ancestor
+            if self.1.is_match(&element) && self.0.match_any(rest) {
+                return true;
             }
             context = rest;
         }
@@ -175,14 +694,27 @@ impl<C: OnlyContextualSelector, A: Selector> OnlyContextualSelector for ContextS
     }
 }
 
+/// Matches the parent in the context, then continues by using the second matcher for the remaining context
+pub struct ParentSelectCons<C, A>(pub C, pub A);
+
+impl<C: OnlyContextualSelector, A: Selector> OnlyContextualSelector for ParentSelectCons<C, A> {
+    fn match_any(&self, context: ElementPath<'_>) -> bool {
+        context.split_last().map_or(false, |(parent, rest)| {
+            self.1.is_match(&parent) && self.0.match_any(rest)
+        })
+    }
+}
+
 /// Matches the element, then continues by using the second matcher for the remaining context
+///
+/// The remaining context is the element's ancestors, without the element itself, so `css_select!(("p") ("p"))` only matches a `p` inside another `p`.
 pub struct ContextualSelectCons<C: OnlyContextualSelector, A: Selector>(pub C, pub A);
 
 impl<C: OnlyContextualSelector, A: Selector> ContextualSelector for ContextualSelectCons<C, A> {
     fn context_match<'a>(&self, item: &Item<'a>) -> bool {
         item.as_element()
             .map_or(false, |element| self.1.is_match(&element))
-            && self.0.match_any(item.as_path())
+            && self.0.match_any(item.context_path())
     }
 }
 
@@ -204,6 +736,59 @@ impl<A: Selector, B: Selector> Selector for AndSelector<A, B> {
     }
 }
 
+/// Combines 2 selectors, selecting elements that either would select, `:is(A, B)`
+pub struct AnySelector<A: Selector, B: Selector>(A, B);
+
+impl<A: Selector, B: Selector> Selector for AnySelector<A, B> {
+    fn is_match(&self, element: &Element<'_>) -> bool {
+        self.0.is_match(element) || self.1.is_match(element)
+    }
+}
+
+/// Selects elements that the selector wouldn't select, `:not(S)`
+pub struct NotSelector<S: Selector>(S);
+
+impl<S: Selector> Selector for NotSelector<S> {
+    fn is_match(&self, element: &Element<'_>) -> bool {
+        !self.0.is_match(element)
+    }
+}
+
+/// Selects the root element of the document, `:root`
+///
+/// This is the root of the document the selector reads, as with `Element::depth`, so after `include` or `group_under`, which only leave ancestors out of the paths, it is still the document's root element, while after `unwrap` the children of an unwrapped root are the roots.
+pub struct RootSelector;
+
+impl Selector for RootSelector {
+    fn is_match(&self, element: &Element<'_>) -> bool {
+        element.depth() == 1
+    }
+}
+
+#[test]
+fn test_context_combinators() {
+    let mut div = crate::iteritem::ElementPathBuf::new();
+    div.append_element("div", vec![("id", "main")]);
+    let mut quote = div.clone();
+    quote.append_element("p", vec![("class", "quote")]);
+    let quote = quote.as_path().as_item().unwrap();
+    // the element isn't one of its own ancestors, these used to match as the element was searched along with its ancestors
+    assert!(!css_select!((."quote") ("p")).context_match(&quote));
+    assert!(!css_select!((#"main") (#"main")).context_match(&div.as_path().as_item().unwrap()));
+    assert!(css_select!((#"main") ("p")).context_match(&quote));
+
+    let mut nested = div.clone();
+    nested
+        .append_element("section", vec![])
+        .append_element("section", vec![])
+        .append_element("p", vec![]);
+    let p = nested.as_path().as_item().unwrap();
+    // the innermost section's parent isn't the div, so the outer section is tried too
+    assert!(css_select!(("div") > ("section")("p")).context_match(&p));
+    assert!(!css_select!(("div") > ("section") > ("p")).context_match(&p));
+    assert!(css_select!(("div")("section")("p")).context_match(&p));
+}
+
 #[test]
 fn test_matchers() {
     let mut path_body = crate::iteritem::ElementPathBuf::new();
@@ -244,4 +829,218 @@ fn test_matchers() {
         .context_match(&path_main.as_path().as_item().unwrap()));
     assert!(!css_select!((#"main") ("p"."quote"))
         .context_match(&body_quote.as_path().as_item().unwrap()));
+    assert!(css_select!(["class"]).context_match(&main_quote.as_path().as_item().unwrap()));
+    assert!(!css_select!(["class"]).context_match(&main_p.as_path().as_item().unwrap()));
+    assert!(
+        css_select!("p"["class"~="quote"]).context_match(&main_quote.as_path().as_item().unwrap())
+    );
+    assert!(
+        css_select!(["class"^="FIXED" i]).context_match(&main_quote.as_path().as_item().unwrap())
+    );
+    assert!(
+        !css_select!(["class" ^= "FIXED"]).context_match(&main_quote.as_path().as_item().unwrap())
+    );
+    assert!(css_select!((["id"="main"]) ("p"["class"$="quote"]))
+        .context_match(&main_quote.as_path().as_item().unwrap()));
+
+    assert!(css_select!(("div") > ("p")).context_match(&main_p.as_path().as_item().unwrap()));
+    assert!(!css_select!(("body") > ("p")).context_match(&main_p.as_path().as_item().unwrap()));
+    assert!(css_select!(("html") ("body") > ("div") > ("p"))
+        .context_match(&main_p.as_path().as_item().unwrap()));
+    assert!(
+        css_select!(("html") > ("body")("p")).context_match(&main_p.as_path().as_item().unwrap())
+    );
+    assert!(
+        !css_select!(("html") > ("div")("p")).context_match(&main_p.as_path().as_item().unwrap())
+    );
+
+    // the element itself isn't part of its context
+    assert!(!css_select!(("p")("p")).context_match(&main_p.as_path().as_item().unwrap()));
+}
+
+#[test]
+fn test_attribute_operators() {
+    use AttributeOperator::*;
+
+    let cases = [
+        (Exact, "en", "en", false, true),
+        (Exact, "en-GB", "en", false, false),
+        (Exact, "EN", "en", true, true),
+        (Includes, "a b  c", "b", false, true),
+        (Includes, "a bc", "b", false, false),
+        (Includes, "a b", "", false, false),
+        (DashMatch, "en-GB", "en", false, true),
+        (DashMatch, "en", "en", false, true),
+        (DashMatch, "eng", "en", false, false),
+        (DashMatch, "EN-gb", "en", true, true),
+        (Prefix, "https://example.com", "https:", false, true),
+        (Prefix, "http://example.com", "https:", false, false),
+        (Prefix, "anything", "", false, false),
+        (Suffix, "photo.PNG", ".png", true, true),
+        (Suffix, "photo.PNG", ".png", false, false),
+        (Suffix, "png", ".png", false, false),
+        (Substring, "a-quote-b", "quote", false, true),
+        (Substring, "a-QUOTE-b", "quote", true, true),
+        (Substring, "a-quot-b", "quote", false, false),
+        (Substring, "ünïcode", "ï", false, true),
+    ];
+    for (operator, value, expected, case_insensitive, result) in cases {
+        assert_eq!(
+            operator.is_match(value, expected, case_insensitive),
+            result,
+            "{:?} {:?} {:?}",
+            operator,
+            value,
+            expected
+        );
+    }
+}
+
+#[test]
+fn test_combinator_slices() {
+    use Combinator::*;
+
+    let mut nested = crate::iteritem::ElementPathBuf::new();
+    nested
+        .append_element("ul", vec![])
+        .append_element("li", vec![])
+        .append_element("ol", vec![])
+        .append_element("li", vec![]);
+    let item = nested.as_path().as_item().unwrap();
+
+    let matches = |selectors: &[(Combinator, &str)]| {
+        let selectors: Vec<_> = selectors
+            .iter()
+            .map(|&(combinator, name)| (combinator, NameSelector(name)))
+            .collect();
+        selectors[..].context_match(&item)
+    };
+    assert!(matches(&[]));
+    assert!(matches(&[(Descendant, "li")]));
+    assert!(matches(&[(Descendant, "ul"), (Descendant, "li")]));
+    assert!(matches(&[(Descendant, "ol"), (Child, "li")]));
+    assert!(!matches(&[(Descendant, "ul"), (Child, "li")]));
+    // the outer li is a child of the ul
+    assert!(matches(&[
+        (Descendant, "ul"),
+        (Child, "li"),
+        (Descendant, "li")
+    ]));
+    assert!(!matches(&[
+        (Descendant, "ul"),
+        (Child, "li"),
+        (Child, "li")
+    ]));
+}
+
+#[test]
+fn test_nth() {
+    let nth = |s| Nth::parse(s).map(|Nth { a, b }| (a, b));
+    assert_eq!(nth("odd"), Some((2, 1)));
+    assert_eq!(nth(" EVEN "), Some((2, 0)));
+    assert_eq!(nth("3"), Some((0, 3)));
+    assert_eq!(nth("-n+3"), Some((-1, 3)));
+    assert_eq!(nth("+n"), Some((1, 0)));
+    assert_eq!(nth("2n + 1"), Some((2, 1)));
+    assert_eq!(nth("10N-2"), Some((10, -2)));
+    assert_eq!(nth(""), None);
+    assert_eq!(nth("n+"), None);
+    assert_eq!(nth("2x"), None);
+    assert_eq!(nth("2n 1"), None);
+    assert_eq!(nth("99999999999999999999"), None);
+    assert_eq!(nth("9223372036854775808"), None);
+    assert_eq!(nth("n+9223372036854775808"), None);
+    assert_eq!(nth("-9223372036854775807"), Some((0, -i64::MAX)));
+
+    let positions = |nth| {
+        (1..=8)
+            .filter(|&i| Nth::parse(nth).unwrap().is_match(i))
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(positions("odd"), [1, 3, 5, 7]);
+    assert_eq!(positions("3n"), [3, 6]);
+    assert_eq!(positions("3n+4"), [4, 7]);
+    assert_eq!(positions("-n+3"), [1, 2, 3]);
+    assert_eq!(positions("-2n+5"), [1, 3, 5]);
+    assert_eq!(positions("5"), [5]);
+    assert_eq!(positions("-9223372036854775807"), []);
+    assert_eq!(positions("9223372036854775807n-9223372036854775807"), []);
+    assert_eq!(positions("-9223372036854775807n+9223372036854775807"), []);
+    assert_eq!(positions("-9223372036854775807n+3"), [3]);
+}
+
+#[test]
+fn test_sibling_matchers() {
+    let mut path = crate::iteritem::ElementPathBuf::new();
+    path.append_element("dl", vec![])
+        .append_element("dt", vec![("class", "term")])
+        .close_element()
+        .append_element("dd", vec![])
+        .close_element()
+        .append_element("dt", vec![])
+        .close_element()
+        .append_element("dd", vec![]);
+    let second_dd = path.as_path().as_item().unwrap();
+
+    assert!(css_select!(("dt") + ("dd")).context_match(&second_dd));
+    assert!(!css_select!((."term") + ("dd")).context_match(&second_dd));
+    assert!(css_select!(("dl") > ("dt") + ("dd")).context_match(&second_dd));
+    assert!(css_select!(("dt") ~ ("dd")).context_match(&second_dd));
+    assert!(!css_select!(("dd") + ("dd")).context_match(&second_dd));
+    assert!(css_select!(("dd") ~ ("dd")).context_match(&second_dd));
+    assert!(!css_select!(("li") ~ ("dd")).context_match(&second_dd));
+    assert!(!css_select!("dd":"first-child").context_match(&second_dd));
+    assert!(css_select!("dl":"first-child")
+        .context_match(&path.as_path().split_last().unwrap().1.as_item().unwrap()));
+    assert!(css_select!("dd":"nth-child"("even")).context_match(&second_dd));
+    assert!(css_select!("dd":"nth-of-type"("2")).context_match(&second_dd));
+    assert!(!css_select!(:"nth-of-type"("1")).context_match(&second_dd));
+    assert!(css_select!(("dl":"first-child") ("dd":"nth-child"("4n"))).context_match(&second_dd));
+}
+
+#[test]
+fn test_logical_matchers() {
+    let mut ad_path = crate::iteritem::ElementPathBuf::new();
+    ad_path
+        .append_element("html", vec![])
+        .append_element("p", vec![("class", "ad")]);
+    let mut p_path = ad_path.clone();
+    p_path.close_element().append_element("p", vec![]);
+    let ad = ad_path.as_path().as_item().unwrap();
+    let p = p_path.as_path().as_item().unwrap();
+
+    assert!(!css_select!("p":"not"(."ad")).context_match(&ad));
+    assert!(css_select!("p":"not"(."ad")).context_match(&p));
+    assert!(!css_select!(:"not"("p", ."ad")).context_match(&p));
+    assert!(css_select!(:"is"("h1", "p")).context_match(&p));
+    assert!(css_select!(:"is"("h1", "p"."ad")).context_match(&ad));
+    assert!(!css_select!(:"is"("h1", "p"."ad")).context_match(&p));
+    assert!(css_select!((:"root") > ("p")).context_match(&p));
+    assert!(!css_select!("p":"root").context_match(&p));
+    assert!(!NameSelector("h1")
+        .or_element(NameSelector("p"))
+        .not()
+        .context_match(&p));
+    assert!(NameSelector("p")
+        .and(ClassSelector("ad").not())
+        .context_match(&p));
+}
+
+#[test]
+fn test_selector_lists() {
+    let mut h2_path = crate::iteritem::ElementPathBuf::new();
+    h2_path
+        .append_element("div", vec![("id", "main")])
+        .append_element("h2", vec![]);
+    let mut p_path = h2_path.clone();
+    p_path.close_element().append_element("p", vec![]);
+    let h2 = h2_path.as_path().as_item().unwrap();
+    let main_p = p_path.as_path().as_item().unwrap();
+
+    assert!(css_select!("h1", "h2", "h3").context_match(&h2));
+    assert!(!css_select!("h1", "h2", "h3").context_match(&main_p));
+    assert!(css_select!((#"main") ("p"), ("aside")).context_match(&main_p));
+    assert!(css_select!(("aside"), (#"main") > ("p")).context_match(&main_p));
+    assert!(!css_select!(("aside"), ("body")("p")).context_match(&main_p));
+    assert!(css_select!("p":"is"("a", "p"), "h1").context_match(&main_p));
 }
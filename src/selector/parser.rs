@@ -0,0 +1,617 @@
+use std::{error, fmt};
+
+use super::{
+    AdjacentSiblingSelector, AttributeOperator, AttributeSelector, ClassSelector,
+    ContextSelectCons, ContextualSelectCons, ContextualSelector, GeneralSiblingSelector,
+    IdSelector, MatchAll, NameSelector, Nth, NthChildSelector, NthOfTypeSelector,
+    OnlyContextualSelector, ParentSelectCons, RootSelector, Selector,
+};
+
+/// An error in the syntax of a selector, each carries the column (counting characters from 1) at which it was detected
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseError {
+    /// A character which isn't allowed at this point in the selector
+    UnexpectedChar { found: char, column: usize },
+    /// The selector ended where more was expected, such as after a combinator or a comma
+    UnexpectedEnd { column: usize },
+    /// Valid css which can't be matched while streaming, such as a pseudo class which depends on later siblings, or a sibling combinator which depends on more than the previous sibling or the names of earlier siblings
+    Unsupported { column: usize },
+}
+
+impl ParseError {
+    /// The column in the selector at which the error was detected, counting characters from 1
+    pub fn column(&self) -> usize {
+        match *self {
+            ParseError::UnexpectedChar { column, .. }
+            | ParseError::UnexpectedEnd { column }
+            | ParseError::Unsupported { column } => column,
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ParseError::UnexpectedChar { found, column } => {
+                write!(f, "unexpected {:?} at column {}", found, column)
+            }
+            ParseError::UnexpectedEnd { column } => {
+                write!(f, "unexpected end of selector at column {}", column)
+            }
+            ParseError::Unsupported { column } => {
+                write!(f, "unsupported selector at column {}", column)
+            }
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+/// Parses a comma separated list of css selectors at runtime, the returned selector owns its strings
+///
+/// Supports element names, `*`, ids, classes, attributes, `:first-child`, `:nth-child()`, `:nth-of-type()`, `:root`, `:is()` and `:not()` with lists of compound selectors, and the descendant, child (`>`) and sibling (`+` and `~`) combinators. The selector before `~` can only be an element name or `*`, and the selector before `+` can't have a sibling combinator itself.
+///
+/// ```
+/// use xmliter::{selector, HtmlIter, HtmlIterator};
+///
+/// let selector = selector::parse("div#main > p.quote, aside").unwrap();
+/// let html = r#"<div id="main"><p class="quote">quoted</p></div><aside>aside</aside>"#;
+/// let out = HtmlIter::from_reader(html.as_bytes()).include(selector);
+/// assert_eq!(out.to_string(), r#"<p class="quote">quoted</p><aside>aside</aside>"#);
+/// ```
+pub fn parse(selector: &str) -> Result<Box<dyn ContextualSelector>, ParseError> {
+    let mut parser = Parser {
+        input: selector,
+        position: 0,
+    };
+    let mut group = parser.complex()?;
+    while parser.eat(',') {
+        group = Box::new(group.or(parser.complex()?));
+    }
+    match parser.peek() {
+        None => Ok(group),
+        Some(_) => Err(parser.unexpected()),
+    }
+}
+
+struct Parser<'a> {
+    input: &'a str,
+    /// Byte position in the input
+    position: usize,
+}
+
+impl<'a> Parser<'a> {
+    fn peek(&self) -> Option<char> {
+        self.input[self.position..].chars().next()
+    }
+
+    fn bump(&mut self) {
+        if let Some(c) = self.peek() {
+            self.position += c.len_utf8();
+        }
+    }
+
+    fn eat(&mut self, expected: char) -> bool {
+        let found = self.peek() == Some(expected);
+        if found {
+            self.bump();
+        }
+        found
+    }
+
+    /// Returns whether any whitespace was skipped
+    fn skip_whitespace(&mut self) -> bool {
+        let start = self.position;
+        while self.peek().map_or(false, char::is_whitespace) {
+            self.bump();
+        }
+        self.position != start
+    }
+
+    fn column(&self, position: usize) -> usize {
+        self.input[..position].chars().count() + 1
+    }
+
+    /// An error for whatever is at the current position
+    fn unexpected(&self) -> ParseError {
+        let column = self.column(self.position);
+        match self.peek() {
+            Some(found) => ParseError::UnexpectedChar { found, column },
+            None => ParseError::UnexpectedEnd { column },
+        }
+    }
+
+    /// Compound selectors separated by combinators, up to a comma or the end
+    fn complex(&mut self) -> Result<Box<dyn ContextualSelector>, ParseError> {
+        self.skip_whitespace();
+        let mut context: Box<dyn OnlyContextualSelector> = Box::new(MatchAll);
+        let mut start = self.position;
+        let mut compound = self.compound()?;
+        // siblings of a sibling aren't kept
+        let mut sibling = false;
+        loop {
+            let end = self.position;
+            let whitespace = self.skip_whitespace();
+            let combinator_position = self.position;
+            let combinator = self.peek().filter(|c| matches!(c, '>' | '+' | '~'));
+            if combinator.is_some() {
+                self.bump();
+                self.skip_whitespace();
+            } else if !whitespace || matches!(self.peek(), None | Some(',')) {
+                break;
+            }
+            let unsupported = ParseError::Unsupported {
+                column: self.column(combinator_position),
+            };
+            let next_start = self.position;
+            let next = self.compound()?;
+            match combinator {
+                Some('+') if !sibling => {
+                    compound = Box::new(AdjacentSiblingSelector(compound, next));
+                    sibling = true;
+                }
+                Some('~') if !sibling => {
+                    let name = self.element_name(start, end).ok_or(unsupported)?;
+                    compound = Box::new(GeneralSiblingSelector(name, next));
+                    sibling = true;
+                }
+                Some('+') | Some('~') => return Err(unsupported),
+                Some(_) => {
+                    context = Box::new(ParentSelectCons(context, compound));
+                    compound = next;
+                    sibling = false;
+                }
+                None => {
+                    context = Box::new(ContextSelectCons(context, compound));
+                    compound = next;
+                    sibling = false;
+                }
+            }
+            start = next_start;
+        }
+        Ok(Box::new(ContextualSelectCons(context, compound)))
+    }
+
+    /// The name of a compound selector between the positions which is only an element name, or `None` for `*`
+    fn element_name(&self, start: usize, end: usize) -> Option<Option<String>> {
+        let mut parser = Parser {
+            input: &self.input[..end],
+            position: start,
+        };
+        let name = if parser.eat('*') {
+            None
+        } else {
+            Some(parser.identifier().ok()?)
+        };
+        (parser.position == end).then_some(name)
+    }
+
+    /// An optional element name or `*`, followed by any number of ids, classes and attributes
+    fn compound(&mut self) -> Result<Box<dyn Selector>, ParseError> {
+        let mut selector: Option<Box<dyn Selector>> = if self.eat('*') {
+            Some(Box::new(MatchAll))
+        } else if self.peek().map_or(false, is_identifier_start) {
+            Some(Box::new(NameSelector(self.identifier()?)))
+        } else {
+            None
+        };
+        loop {
+            let next: Box<dyn Selector> = if self.eat('#') {
+                Box::new(IdSelector(self.identifier()?))
+            } else if self.eat('.') {
+                Box::new(ClassSelector(self.identifier()?))
+            } else if self.eat('[') {
+                Box::new(self.attribute()?)
+            } else if self.eat(':') {
+                self.pseudo_class()?
+            } else {
+                break;
+            };
+            selector = Some(match selector {
+                Some(selector) => Box::new(selector.and(next)),
+                None => next,
+            });
+        }
+        selector.ok_or_else(|| self.unexpected())
+    }
+
+    /// The rest of a pseudo class after the `:`
+    fn pseudo_class(&mut self) -> Result<Box<dyn Selector>, ParseError> {
+        let start = self.position;
+        let name = self.identifier()?;
+        match name.to_ascii_lowercase().as_str() {
+            "first-child" => Ok(Box::new(NthChildSelector(Nth::FIRST))),
+            "nth-child" => Ok(Box::new(NthChildSelector(self.nth()?))),
+            "nth-of-type" => Ok(Box::new(NthOfTypeSelector(self.nth()?))),
+            "root" => Ok(Box::new(RootSelector)),
+            "is" => self.compound_list(),
+            "not" => Ok(Box::new(self.compound_list()?.not())),
+            _ => Err(ParseError::Unsupported {
+                column: self.column(start),
+            }),
+        }
+    }
+
+    /// The `(a, b)` argument of a pseudo class, selecting elements which any of the compound selectors would select
+    fn compound_list(&mut self) -> Result<Box<dyn Selector>, ParseError> {
+        if !self.eat('(') {
+            return Err(self.unexpected());
+        }
+        self.skip_whitespace();
+        let mut selector = self.compound()?;
+        self.skip_whitespace();
+        while self.eat(',') {
+            self.skip_whitespace();
+            selector = Box::new(selector.or_element(self.compound()?));
+            self.skip_whitespace();
+        }
+        if !self.eat(')') {
+            return Err(self.unexpected());
+        }
+        Ok(selector)
+    }
+
+    /// The `(an+b)` argument of a pseudo class
+    fn nth(&mut self) -> Result<Nth, ParseError> {
+        if !self.eat('(') {
+            return Err(self.unexpected());
+        }
+        let start = self.position;
+        let end = match self.input[start..].find(')') {
+            Some(length) => start + length,
+            None => {
+                self.position = self.input.len();
+                return Err(self.unexpected());
+            }
+        };
+        let nth = Nth::parse(&self.input[start..end]).ok_or_else(|| self.unexpected())?;
+        self.position = end + 1;
+        Ok(nth)
+    }
+
+    /// The rest of an attribute selector after the `[`, such as `lang|="en" i]`
+    fn attribute(&mut self) -> Result<AttributeSelector<String>, ParseError> {
+        self.skip_whitespace();
+        let name = self.identifier()?;
+        self.skip_whitespace();
+        if self.eat(']') {
+            return Ok(AttributeSelector {
+                name,
+                value: None,
+                case_insensitive: false,
+            });
+        }
+        let operator = match self.peek() {
+            Some('=') => AttributeOperator::Exact,
+            Some('~') => AttributeOperator::Includes,
+            Some('|') => AttributeOperator::DashMatch,
+            Some('^') => AttributeOperator::Prefix,
+            Some('$') => AttributeOperator::Suffix,
+            Some('*') => AttributeOperator::Substring,
+            _ => return Err(self.unexpected()),
+        };
+        self.bump();
+        if operator != AttributeOperator::Exact && !self.eat('=') {
+            return Err(self.unexpected());
+        }
+        self.skip_whitespace();
+        let value = match self.peek() {
+            Some(quote) if quote == '"' || quote == '\'' => {
+                self.bump();
+                self.string(quote)?
+            }
+            _ => self.identifier()?,
+        };
+        self.skip_whitespace();
+        let flag = match self.peek() {
+            Some('i') | Some('I') => Some(true),
+            Some('s') | Some('S') => Some(false),
+            _ => None,
+        };
+        if flag.is_some() {
+            self.bump();
+            self.skip_whitespace();
+        }
+        if !self.eat(']') {
+            return Err(self.unexpected());
+        }
+        Ok(AttributeSelector {
+            name,
+            value: Some((operator, value)),
+            case_insensitive: flag.unwrap_or(false),
+        })
+    }
+
+    /// The rest of a quoted string after the opening quote
+    fn string(&mut self, quote: char) -> Result<String, ParseError> {
+        let mut string = String::new();
+        loop {
+            match self.peek() {
+                None => return Err(self.unexpected()),
+                Some(c) if c == quote => {
+                    self.bump();
+                    return Ok(string);
+                }
+                Some('\\') => {
+                    self.bump();
+                    // an escaped newline continues the string
+                    if !self.eat('\n') {
+                        string.push(self.escape()?);
+                    }
+                }
+                Some(c) => {
+                    self.bump();
+                    string.push(c);
+                }
+            }
+        }
+    }
+
+    fn identifier(&mut self) -> Result<String, ParseError> {
+        let mut identifier = String::new();
+        while let Some(c) = self.peek() {
+            if c == '\\' {
+                self.bump();
+                identifier.push(self.escape()?);
+            } else if is_identifier_char(c) {
+                self.bump();
+                identifier.push(c);
+            } else {
+                break;
+            }
+        }
+        if identifier.is_empty() {
+            Err(self.unexpected())
+        } else {
+            Ok(identifier)
+        }
+    }
+
+    /// Up to 6 hex digits and an optional whitespace, or any other character taken literally
+    fn escape(&mut self) -> Result<char, ParseError> {
+        let start = self.position;
+        while self.position - start < 6 && self.peek().map_or(false, |c| c.is_ascii_hexdigit()) {
+            self.bump();
+        }
+        if self.position == start {
+            let c = self.peek().ok_or_else(|| self.unexpected())?;
+            self.bump();
+            return Ok(c);
+        }
+        let code = u32::from_str_radix(&self.input[start..self.position], 16).unwrap();
+        if self.peek().map_or(false, char::is_whitespace) {
+            self.bump();
+        }
+        Ok(char::from_u32(code)
+            .filter(|&c| c != '\0')
+            .unwrap_or(char::REPLACEMENT_CHARACTER))
+    }
+}
+
+fn is_identifier_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_' || c == '-' || c == '\\' || !c.is_ascii()
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_' || c == '-' || !c.is_ascii()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::iteritem::ElementPathBuf;
+
+    fn matches(selector: &str, path: &ElementPathBuf) -> bool {
+        parse(selector)
+            .unwrap()
+            .context_match(&path.as_path().as_item().unwrap())
+    }
+
+    #[test]
+    fn compound_selectors() {
+        let mut path = ElementPathBuf::new();
+        path.append_element("p", vec![("id", "intro"), ("class", "fixed quote")]);
+
+        assert!(matches("p", &path));
+        assert!(matches("*", &path));
+        assert!(matches("#intro", &path));
+        assert!(matches("p.quote", &path));
+        assert!(matches("*.fixed.quote#intro", &path));
+        assert!(!matches("div", &path));
+        assert!(!matches("p.bloat", &path));
+        assert!(!matches("p#main", &path));
+        assert!(matches(r"\70 .\71 uote", &path));
+    }
+
+    #[test]
+    fn attribute_selectors() {
+        let mut path = ElementPathBuf::new();
+        path.append_element(
+            "a",
+            vec![("href", "https://example.com/Page"), ("lang", "en-GB")],
+        );
+
+        assert!(matches("[href]", &path));
+        assert!(matches("a[ href ]", &path));
+        assert!(!matches("[title]", &path));
+        assert!(matches("[lang=en-GB]", &path));
+        assert!(matches("[lang|=en]", &path));
+        assert!(matches(r#"[href^="https://"]"#, &path));
+        assert!(matches("[href$='page' i]", &path));
+        assert!(!matches("[href$='page']", &path));
+        assert!(!matches("[href$='page' s]", &path));
+        assert!(matches(r#"[href*="example"][lang~=en-GB]"#, &path));
+        assert!(matches(r#"[href="https://example.com/\50 age"]"#, &path));
+    }
+
+    #[test]
+    fn combinators() {
+        let mut path = ElementPathBuf::new();
+        path.append_element("div", vec![("id", "main")])
+            .append_element("blockquote", vec![])
+            .append_element("p", vec![("class", "quote")]);
+
+        assert!(matches("div p", &path));
+        assert!(matches("  div   blockquote  p  ", &path));
+        assert!(matches("div > blockquote > p", &path));
+        assert!(matches("div>blockquote>p", &path));
+        assert!(matches("#main > * p.quote", &path));
+        assert!(!matches("div > p", &path));
+        assert!(!matches("p p", &path));
+        assert!(!matches("blockquote div p", &path));
+    }
+
+    #[test]
+    fn child_combinator_backtracks() {
+        let mut path = ElementPathBuf::new();
+        path.append_element("ul", vec![])
+            .append_element("li", vec![])
+            .append_element("div", vec![])
+            .append_element("li", vec![])
+            .append_element("p", vec![]);
+
+        // the nearest li isn't a child of a ul, but a more distant one is
+        assert!(matches("ul > li p", &path));
+        assert!(!matches("ol > li p", &path));
+    }
+
+    #[test]
+    fn siblings() {
+        let mut path = ElementPathBuf::new();
+        path.append_element("ul", vec![])
+            .append_element("li", vec![("class", "new")])
+            .close_element()
+            .append_element("li", vec![])
+            .close_element()
+            .append_element("li", vec![]);
+
+        assert!(matches("li + li", &path));
+        assert!(matches("ul > li ~ li", &path));
+        assert!(matches("* ~ li", &path));
+        assert!(!matches("li.new + li", &path));
+        assert!(matches("li:nth-child(3)", &path));
+        assert!(matches("li:NTH-OF-TYPE( 2n + 1 )", &path));
+        assert!(matches("ul:first-child li:nth-child(odd)", &path));
+        assert!(!matches("li:first-child", &path));
+    }
+
+    #[test]
+    fn logical_pseudo_classes() {
+        let mut path = ElementPathBuf::new();
+        path.append_element("html", vec![])
+            .append_element("h2", vec![("class", "title")]);
+
+        assert!(matches(":root > h2", &path));
+        assert!(!matches("h2:root", &path));
+        assert!(matches("html:root", path.clone().close_element()));
+        assert!(matches(":is(h1, h2.title)", &path));
+        assert!(!matches(":is(h1,h3)", &path));
+        assert!(matches("h2:not(.ad)", &path));
+        assert!(!matches("h2:not( .ad , .title )", &path));
+        assert!(matches("*:not(:root)", &path));
+    }
+
+    #[test]
+    fn selector_lists() {
+        let mut path = ElementPathBuf::new();
+        path.append_element("aside", vec![]);
+
+        assert!(matches("div#main > p.quote, aside", &path));
+        assert!(matches("aside,p", &path));
+        assert!(!matches("div, p", &path));
+    }
+
+    #[test]
+    fn errors() {
+        fn error(selector: &str) -> ParseError {
+            parse(selector).err().unwrap()
+        }
+
+        assert_eq!(error(""), ParseError::UnexpectedEnd { column: 1 });
+        assert_eq!(error("div >"), ParseError::UnexpectedEnd { column: 6 });
+        assert_eq!(error("div,"), ParseError::UnexpectedEnd { column: 5 });
+        assert_eq!(
+            error("div > > p"),
+            ParseError::UnexpectedChar {
+                found: '>',
+                column: 7
+            }
+        );
+        assert_eq!(
+            error("p.!"),
+            ParseError::UnexpectedChar {
+                found: '!',
+                column: 3
+            }
+        );
+        assert_eq!(
+            error("ünïcode p$"),
+            ParseError::UnexpectedChar {
+                found: '$',
+                column: 10
+            }
+        );
+        assert_eq!(error("p#"), ParseError::UnexpectedEnd { column: 3 });
+        assert_eq!(
+            error("[lang!=en]"),
+            ParseError::UnexpectedChar {
+                found: '!',
+                column: 6
+            }
+        );
+        assert_eq!(
+            error("[lang|en]"),
+            ParseError::UnexpectedChar {
+                found: 'e',
+                column: 7
+            }
+        );
+        assert_eq!(
+            error("[lang=en x]"),
+            ParseError::UnexpectedChar {
+                found: 'x',
+                column: 10
+            }
+        );
+        assert_eq!(
+            error("[lang='en]"),
+            ParseError::UnexpectedEnd { column: 11 }
+        );
+        assert_eq!(error("a + b + c"), ParseError::Unsupported { column: 7 });
+        assert_eq!(error("a + b ~ c"), ParseError::Unsupported { column: 7 });
+        assert_eq!(error("a.b ~ c"), ParseError::Unsupported { column: 5 });
+        assert_eq!(error("li:hover"), ParseError::Unsupported { column: 4 });
+        assert_eq!(
+            error(":is(div p)"),
+            ParseError::UnexpectedChar {
+                found: 'p',
+                column: 9
+            }
+        );
+        assert_eq!(error(":not(p"), ParseError::UnexpectedEnd { column: 7 });
+        assert_eq!(
+            error("li:nth-child(9223372036854775808)"),
+            ParseError::UnexpectedChar {
+                found: '9',
+                column: 14
+            }
+        );
+        // the largest values parse and match without overflowing
+        let mut path = ElementPathBuf::new();
+        path.append_element("li", vec![]);
+        assert!(!matches("li:nth-child(-9223372036854775807)", &path));
+        assert!(matches("li:nth-child(-n+9223372036854775807)", &path));
+        assert_eq!(
+            error("li:nth-child(2x)"),
+            ParseError::UnexpectedChar {
+                found: '2',
+                column: 14
+            }
+        );
+        assert_eq!(
+            error("li:nth-child(2"),
+            ParseError::UnexpectedEnd { column: 15 }
+        );
+        assert_eq!(error("p#").column(), 3);
+    }
+}
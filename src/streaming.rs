@@ -0,0 +1,113 @@
+//! A `StreamingIterator` over the items of an `HtmlIterator`, with `HtmlIterator::streaming`
+//!
+//! `StreamingIterator::get` can only return a reference to something the iterator owns, while an `Item` borrows the iterator, so the streamed item is a `Current`, which reads the current item with the same methods as `Item`.
+
+use std::{borrow::Cow, fmt};
+
+use crate::{Element, ElementPath, Error, HtmlIterator, Item};
+
+/// A `StreamingIterator` over an iterator's items, which ends at the first error reading the document rather than panicking
+pub struct Streaming<I> {
+    current: Current<I>,
+    error: Option<Error>,
+}
+
+impl<I: HtmlIterator> Streaming<I> {
+    pub(crate) fn new(iter: I) -> Self {
+        Streaming {
+            current: Current(iter),
+            error: None,
+        }
+    }
+
+    /// The error which ended the stream, if the document couldn't be read
+    pub fn error(&self) -> Option<&Error> {
+        self.error.as_ref()
+    }
+
+    pub fn into_inner(self) -> I {
+        self.current.0
+    }
+}
+
+impl<I: HtmlIterator> streaming_iterator::StreamingIterator for Streaming<I> {
+    type Item = Current<I>;
+
+    fn advance(&mut self) {
+        if self.error.is_none() {
+            if let Err(error) = self.current.0.try_advance() {
+                self.error = Some(error);
+            }
+        }
+    }
+
+    fn get(&self) -> Option<&Current<I>> {
+        match self.error {
+            None => self.current.0.get().map(|_| &self.current),
+            Some(_) => None,
+        }
+    }
+}
+
+/// The current item of a `Streaming` iterator, with the methods of `Item`
+pub struct Current<I>(I);
+
+impl<I: HtmlIterator> Current<I> {
+    /// The current item, which the other methods read
+    pub fn item(&self) -> Item<'_> {
+        // only streamed while the iterator has an item
+        self.0.get().unwrap()
+    }
+
+    pub fn as_element(&self) -> Option<Element<'_>> {
+        self.item().as_element()
+    }
+
+    pub fn context_path(&self) -> ElementPath<'_> {
+        self.item().into_context_path()
+    }
+
+    pub fn as_path(&self) -> ElementPath<'_> {
+        self.item().as_path()
+    }
+
+    pub fn name(&self) -> Option<&str> {
+        self.item().name()
+    }
+
+    pub fn attr(&self, name: &str) -> Option<&str> {
+        self.item().attr(name)
+    }
+
+    pub fn first_ancestor(&self) -> Option<Element<'_>> {
+        self.item().first_ancestor()
+    }
+
+    pub fn depth(&self) -> usize {
+        self.item().depth()
+    }
+
+    pub fn text(&self) -> Option<Cow<'_, str>> {
+        self.item().text()
+    }
+
+    pub fn comment(&self) -> Option<&str> {
+        self.item().comment()
+    }
+
+    pub fn cdata(&self) -> Option<&str> {
+        self.item().cdata()
+    }
+}
+
+impl<I: HtmlIterator> fmt::Display for Current<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.item().fmt(f)
+    }
+}
+
+impl<I: HtmlIterator> fmt::Debug for Current<I> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(&self.item(), f)
+    }
+}
@@ -0,0 +1,122 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use std::io;
+
+use xmliter::{
+    css_select, map::EditAttributes, selector::ContextualSelector, HtmlIter, HtmlIterator,
+    HtmlWriter, Item,
+};
+
+struct CountingAllocator;
+
+thread_local! {
+    /// Counted per thread, as the tests run in parallel
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+fn allocations() -> usize {
+    ALLOCATIONS.with(Cell::get)
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// A document with the content repeated inside a root element
+fn document(content: &str) -> String {
+    let mut doc = String::from("<root>");
+    for _ in 0..1000 {
+        doc.push_str(content);
+    }
+    doc.push_str("</root>");
+    doc
+}
+
+/// Calls `check` with each item, asserting that nothing is allocated after the first 100 items
+fn assert_no_allocations_after_warm_up(mut iter: impl HtmlIterator, mut check: impl FnMut(Item)) {
+    for _ in 0..100 {
+        check(iter.next().unwrap());
+    }
+    let before = allocations();
+    while let Some(item) = iter.next() {
+        check(item);
+    }
+    assert_eq!(allocations(), before);
+}
+
+#[test]
+fn elements_dont_allocate_after_warm_up() {
+    let doc =
+        document(r#"<div class="item" id="a"><p class="quote" title="x &amp; y"><br/></p></div>"#);
+    let selector = css_select!(("div") ("p"."quote"));
+    let mut matches = 0;
+    assert_no_allocations_after_warm_up(HtmlIter::from_reader(doc.as_bytes()), |item| {
+        if selector.context_match(&item) {
+            matches += 1;
+        }
+    });
+    assert!(matches > 0);
+}
+
+#[test]
+fn writing_items_doesnt_allocate_after_warm_up() {
+    let doc = document(r#"<p class="a &amp; b">fish &amp; chips</p><p>peas<br/></p>"#);
+    let mut writer = HtmlWriter::from_writer(io::sink());
+    let mut html_writer = HtmlWriter::from_html_writer(io::sink());
+    assert_no_allocations_after_warm_up(HtmlIter::from_reader(doc.as_bytes()), |item| {
+        writer.write_item(item).unwrap();
+        html_writer.write_item(item).unwrap();
+    });
+    let before = allocations();
+    writer.finish().unwrap();
+    html_writer.finish().unwrap();
+    assert_eq!(allocations(), before);
+}
+
+#[test]
+fn mapping_elements_doesnt_allocate_after_warm_up() {
+    let doc = document(r#"<div class="item" id="a"><p class="quote"><br id="b"/></p></div>"#);
+    let selector = css_select!(("section") ("section"."quote"));
+    let iter = HtmlIter::from_reader(doc.as_bytes())
+        .map_all(|element| {
+            element
+                .rename("section")
+                .map_attributes(|attributes| attributes.filter(|name, _value| name != "id"))
+        })
+        .exclude(css_select!("br"));
+    let mut matches = 0;
+    assert_no_allocations_after_warm_up(iter, |item| {
+        if selector.context_match(&item) {
+            matches += 1;
+        }
+    });
+    assert!(matches > 0);
+}
+
+#[test]
+fn unwrapping_elements_doesnt_allocate_after_warm_up() {
+    let doc =
+        document(r#"<div><font color="red"><span>a</span><p class="quote">b</p></font></div>"#);
+    let selector = css_select!(("div") > ("p"."quote"));
+    let iter = HtmlIter::from_reader(doc.as_bytes()).unwrap(css_select!("font", "span"));
+    let mut matches = 0;
+    assert_no_allocations_after_warm_up(iter, |item| {
+        if selector.context_match(&item) {
+            matches += 1;
+        }
+    });
+    assert!(matches > 0);
+}
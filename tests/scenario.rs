@@ -24,7 +24,7 @@ fn include_for() {
     while let Some(item) = iter.next() {
         if let Some(under_main) = item.include(&css_select!("main")) {
             // anything without main in the path is ignored, and any context with main is stripped before the main
-            writer.write_item(under_main);
+            writer.write_item(under_main).unwrap();
         }
     }
     assert_eq!(String::from_utf8(out).unwrap(), "<main>content</main>");
@@ -55,7 +55,7 @@ fn exclude_for() {
     while let Some(item) = iter.next() {
         if !css_select!(("main") (."bloat")).match_any(item.as_path()) {
             // if nothing in the item's path matches
-            writer.write_item(item);
+            writer.write_item(item).unwrap();
         }
     }
     assert_eq!(
@@ -63,3 +63,146 @@ fn exclude_for() {
         "<!DOCTYPE html><html><body><main><p>content</p></main></body></html>"
     );
 }
+
+#[test]
+fn exclude_siblings() {
+    let read = "<ul><li>a</li><li>b</li><li>c</li></ul><ol><li>d</li><li>e</li></ol>";
+    let out = HtmlIter::from_reader(read.as_bytes())
+        .exclude(css_select!(("li") + ("li")))
+        .to_string();
+    assert_eq!(out, "<ul><li>a</li></ul><ol><li>d</li></ol>");
+    let out = HtmlIter::from_reader(read.as_bytes())
+        .exclude(css_select!(("ol") > ("li":"nth-of-type"("1"))))
+        .to_string();
+    assert_eq!(
+        out,
+        "<ul><li>a</li><li>b</li><li>c</li></ul><ol><li>e</li></ol>"
+    );
+}
+
+#[test]
+fn exclude_selector_list() {
+    let read = r#"<body><nav>links</nav><main><p>content</p><p class="ad">buy</p></main><footer>bye</footer></body>"#;
+    let out = HtmlIter::from_reader(read.as_bytes())
+        .exclude(css_select!("nav", "footer", ("main") (."ad")))
+        .to_string();
+    assert_eq!(out, "<body><main><p>content</p></main></body>");
+}
+
+#[test]
+fn extract_links() {
+    let read = BufReader::new(Cursor::new(
+        r#"<html><body><a href="/a">a</a><link href="style.css"/><p><a href="/b">b</a><a>c</a></p></body></html>"#,
+    ));
+    let links: Vec<String> = HtmlIter::from_reader(read)
+        .filter_map(|item| {
+            item.attr("href")
+                .filter(|_| item.name() == Some("a"))
+                .map(str::to_string)
+        })
+        .collect();
+    assert_eq!(links, vec!["/a", "/b"]);
+}
+
+#[test]
+fn extract_rdfa_books() {
+    #[derive(Debug, PartialEq)]
+    struct Book {
+        name: String,
+        description: Option<String>,
+        author: Option<String>,
+    }
+
+    let read = BufReader::new(Cursor::new(
+        r#"<html><body>
+            <div vocab="https://schema.org/" typeof="Book">
+                <h2 property="name">Dune</h2>
+                <p property="description">A <em>desert</em> planet</p>
+                <span property="author">Frank Herbert</span>
+            </div>
+            <div vocab="https://schema.org/" typeof="Book"><p property="description">No name</p></div>
+            <div vocab="https://schema.org/" typeof="Movie"><h2 property="name">Alien</h2></div>
+            <div vocab="https://schema.org/" typeof="Book"><h2 property="name">Emma</h2></div>
+        </body></html>"#,
+    ));
+    let books: Vec<Book> = HtmlIter::from_reader(read)
+        .group_under(css_select!(
+            (["vocab" = "https://schema.org/"]["typeof" = "Book"])
+        ))
+        .filter_map(|book| {
+            let mut name = String::new();
+            let mut description = String::new();
+            let mut author = String::new();
+            while let Some(item) = book.next() {
+                if let Some(text) = item.text() {
+                    match item
+                        .context_path()
+                        .iter()
+                        .rev()
+                        .find_map(|ancestor| ancestor.attr("property"))
+                    {
+                        Some("name") => name.push_str(&text),
+                        Some("description") => description.push_str(&text),
+                        Some("author") => author.push_str(&text),
+                        _ => {}
+                    }
+                }
+            }
+            if !name.is_empty() {
+                Some(Book {
+                    name,
+                    description: (!description.is_empty()).then_some(description),
+                    author: (!author.is_empty()).then_some(author),
+                })
+            } else {
+                None
+            }
+        })
+        .collect();
+    assert_eq!(
+        books,
+        vec![
+            Book {
+                name: "Dune".to_string(),
+                description: Some("A desert planet".to_string()),
+                author: Some("Frank Herbert".to_string()),
+            },
+            Book {
+                name: "Emma".to_string(),
+                description: None,
+                author: None,
+            },
+        ]
+    );
+}
+
+#[test]
+fn include_display() {
+    use std::{fmt::Write as _, io::Write as _};
+
+    let read = BufReader::new(Cursor::new(
+        "<!DOCTYPE html><html><body><main>content</main></body></html>",
+    ));
+    let mut write = Vec::new();
+    write!(
+        &mut write,
+        "{}",
+        HtmlIter::from_reader(read)
+            .include(css_select!("main"))
+            .display()
+    )
+    .unwrap();
+    assert_eq!(String::from_utf8(write).unwrap(), "<main>content</main>");
+
+    let read = BufReader::new(Cursor::new(
+        "<!DOCTYPE html><html><body><main>content</main></body></html>",
+    ));
+    let mut iter = HtmlIter::from_reader(read);
+    let mut write = String::new();
+    while let Some(item) = iter.next() {
+        if let Some(under_main) = item.include(&css_select!("main")) {
+            write!(&mut write, "{}", under_main).unwrap();
+        }
+    }
+    assert_eq!(write, "<main>content</main>");
+}